@@ -9,19 +9,49 @@ use group::{CurveAffine, CurveProjective};
 use pairing::bls12_381::*;
 use pistis::poe::*;
 use pistis::ro::RO;
+use rand::Rng;
 use sha3::Sha3_256;
 
-type Fischlin<C> =
-    FischlinTransform<DualProofOfExponentSigmaProtocol<C>, Sha3_256>;
-type FiatShamir<C> =
-    FiatShamirTransform<DualProofOfExponentSigmaProtocol<C>, Sha3_256>;
+type Dual<C> = DualProofOfExponentSigmaProtocol<C>;
+type Fischlin<S> = FischlinTransform<S, Sha3_256>;
+type FischlinWith<P> = FischlinTransform<Dual<G1Affine>, Sha3_256, P>;
+type FiatShamir<S> = FiatShamirTransform<S, Sha3_256>;
 
-fn bench_prove<
-    C: CurveAffine,
-    N: NIZK<X = CurvePair<C>, W = FieldPair<C::Scalar>>,
->(
-    b: &mut Bencher,
-) {
+/// Samples a statement and witness to benchmark a sigma protocol with.
+trait Sample: SigmaProtocol {
+    fn sample<R: Rng + ?Sized>(rng: &mut R) -> (Self::X, Self::W);
+}
+
+impl<C: CurveAffine> Sample for Dual<C> {
+    fn sample<R: Rng + ?Sized>(rng: &mut R) -> (Self::X, Self::W) {
+        let (a, b) = (C::Scalar::random(rng), C::Scalar::random(rng));
+        (
+            CurvePair::new(
+                C::one().mul(a).into_affine(),
+                C::one().mul(b).into_affine(),
+            ),
+            FieldPair::new(a, b),
+        )
+    }
+}
+
+impl<C: CurveAffine> Sample for DLEQSigmaProtocol<C> {
+    fn sample<R: Rng + ?Sized>(rng: &mut R) -> (Self::X, Self::W) {
+        let a = C::Scalar::random(rng);
+        let u = C::one().mul(C::Scalar::random(rng)).into_affine();
+        (
+            DLEQStatement::new(
+                C::one(),
+                u,
+                C::one().mul(a).into_affine(),
+                u.mul(a).into_affine(),
+            ),
+            FieldElement::new(a),
+        )
+    }
+}
+
+fn bench_prove<S: Sample, N: NIZK<X = S::X, W = S::W>>(b: &mut Bencher) {
     b.iter_batched(
         || {
             let mut rng = Sha3_256::query(&[
@@ -29,16 +59,8 @@ fn bench_prove<
                 0xe2, 0x7e, 0xa8, 0x97, 0x34, 0xb9,
             ])
             .into_rng();
-            let (a, b) =
-                (C::Scalar::random(&mut rng), C::Scalar::random(&mut rng));
-            (
-                CurvePair::new(
-                    C::one().mul(a).into_affine(),
-                    C::one().mul(b).into_affine(),
-                ),
-                FieldPair::new(a, b),
-                rng,
-            )
+            let (x, w) = S::sample(&mut rng);
+            (x, w, rng)
         },
         |(x, w, mut rng)| N::prove(&x, &w, &mut rng),
         BatchSize::SmallInput,
@@ -47,23 +69,74 @@ fn bench_prove<
 
 fn bench_prove_all(c: &mut Criterion) {
     let mut fischlin = c.benchmark_group("prove Fischlin");
-    fischlin.bench_function("G1", bench_prove::<G1Affine, Fischlin<G1Affine>>);
-    fischlin.bench_function("G2", bench_prove::<G2Affine, Fischlin<G2Affine>>);
+    fischlin.bench_function(
+        "G1",
+        bench_prove::<Dual<G1Affine>, Fischlin<Dual<G1Affine>>>,
+    );
+    fischlin.bench_function(
+        "G2",
+        bench_prove::<Dual<G2Affine>, Fischlin<Dual<G2Affine>>>,
+    );
+    fischlin.bench_function(
+        "G1 DLEQ",
+        bench_prove::<
+            DLEQSigmaProtocol<G1Affine>,
+            Fischlin<DLEQSigmaProtocol<G1Affine>>,
+        >,
+    );
+    fischlin.bench_function(
+        "G2 DLEQ",
+        bench_prove::<
+            DLEQSigmaProtocol<G2Affine>,
+            Fischlin<DLEQSigmaProtocol<G2Affine>>,
+        >,
+    );
     fischlin.finish();
+    let mut params = c.benchmark_group("prove Fischlin parameters");
+    params.bench_function(
+        "default",
+        bench_prove::<Dual<G1Affine>, FischlinWith<FischlinDefault>>,
+    );
+    params.bench_function(
+        "80",
+        bench_prove::<Dual<G1Affine>, FischlinWith<Fischlin80>>,
+    );
+    params.bench_function(
+        "112",
+        bench_prove::<Dual<G1Affine>, FischlinWith<Fischlin112>>,
+    );
+    params.bench_function(
+        "128",
+        bench_prove::<Dual<G1Affine>, FischlinWith<Fischlin128>>,
+    );
+    params.finish();
     let mut fiatshamir = c.benchmark_group("prove Fiat-Shamir");
-    fiatshamir
-        .bench_function("G1", bench_prove::<G1Affine, FiatShamir<G1Affine>>);
-    fiatshamir
-        .bench_function("G2", bench_prove::<G2Affine, FiatShamir<G2Affine>>);
+    fiatshamir.bench_function(
+        "G1",
+        bench_prove::<Dual<G1Affine>, FiatShamir<Dual<G1Affine>>>,
+    );
+    fiatshamir.bench_function(
+        "G2",
+        bench_prove::<Dual<G2Affine>, FiatShamir<Dual<G2Affine>>>,
+    );
+    fiatshamir.bench_function(
+        "G1 DLEQ",
+        bench_prove::<
+            DLEQSigmaProtocol<G1Affine>,
+            FiatShamir<DLEQSigmaProtocol<G1Affine>>,
+        >,
+    );
+    fiatshamir.bench_function(
+        "G2 DLEQ",
+        bench_prove::<
+            DLEQSigmaProtocol<G2Affine>,
+            FiatShamir<DLEQSigmaProtocol<G2Affine>>,
+        >,
+    );
     fiatshamir.finish();
 }
 
-fn bench_verify<
-    C: CurveAffine,
-    N: NIZK<X = CurvePair<C>, W = FieldPair<C::Scalar>>,
->(
-    b: &mut Bencher,
-) {
+fn bench_verify<S: Sample, N: NIZK<X = S::X, W = S::W>>(b: &mut Bencher) {
     b.iter_batched(
         || {
             let mut rng = Sha3_256::query(&[
@@ -71,15 +144,7 @@ fn bench_verify<
                 0xe2, 0x7e, 0xa8, 0x97, 0x34, 0xb9,
             ])
             .into_rng();
-            let (a, b) =
-                (C::Scalar::random(&mut rng), C::Scalar::random(&mut rng));
-            let (x, w) = (
-                CurvePair::new(
-                    C::one().mul(a).into_affine(),
-                    C::one().mul(b).into_affine(),
-                ),
-                FieldPair::new(a, b),
-            );
+            let (x, w) = S::sample(&mut rng);
             let pi = N::prove(&x, &w, &mut rng);
             (x, pi)
         },
@@ -90,14 +155,70 @@ fn bench_verify<
 
 fn bench_verify_all(c: &mut Criterion) {
     let mut fischlin = c.benchmark_group("verify Fischlin");
-    fischlin.bench_function("G1", bench_verify::<G1Affine, Fischlin<G1Affine>>);
-    fischlin.bench_function("G2", bench_verify::<G2Affine, Fischlin<G2Affine>>);
+    fischlin.bench_function(
+        "G1",
+        bench_verify::<Dual<G1Affine>, Fischlin<Dual<G1Affine>>>,
+    );
+    fischlin.bench_function(
+        "G2",
+        bench_verify::<Dual<G2Affine>, Fischlin<Dual<G2Affine>>>,
+    );
+    fischlin.bench_function(
+        "G1 DLEQ",
+        bench_verify::<
+            DLEQSigmaProtocol<G1Affine>,
+            Fischlin<DLEQSigmaProtocol<G1Affine>>,
+        >,
+    );
+    fischlin.bench_function(
+        "G2 DLEQ",
+        bench_verify::<
+            DLEQSigmaProtocol<G2Affine>,
+            Fischlin<DLEQSigmaProtocol<G2Affine>>,
+        >,
+    );
     fischlin.finish();
+    let mut params = c.benchmark_group("verify Fischlin parameters");
+    params.bench_function(
+        "default",
+        bench_verify::<Dual<G1Affine>, FischlinWith<FischlinDefault>>,
+    );
+    params.bench_function(
+        "80",
+        bench_verify::<Dual<G1Affine>, FischlinWith<Fischlin80>>,
+    );
+    params.bench_function(
+        "112",
+        bench_verify::<Dual<G1Affine>, FischlinWith<Fischlin112>>,
+    );
+    params.bench_function(
+        "128",
+        bench_verify::<Dual<G1Affine>, FischlinWith<Fischlin128>>,
+    );
+    params.finish();
     let mut fiatshamir = c.benchmark_group("verify Fiat-Shamir");
-    fiatshamir
-        .bench_function("G1", bench_verify::<G1Affine, FiatShamir<G1Affine>>);
-    fiatshamir
-        .bench_function("G2", bench_verify::<G2Affine, FiatShamir<G2Affine>>);
+    fiatshamir.bench_function(
+        "G1",
+        bench_verify::<Dual<G1Affine>, FiatShamir<Dual<G1Affine>>>,
+    );
+    fiatshamir.bench_function(
+        "G2",
+        bench_verify::<Dual<G2Affine>, FiatShamir<Dual<G2Affine>>>,
+    );
+    fiatshamir.bench_function(
+        "G1 DLEQ",
+        bench_verify::<
+            DLEQSigmaProtocol<G1Affine>,
+            FiatShamir<DLEQSigmaProtocol<G1Affine>>,
+        >,
+    );
+    fiatshamir.bench_function(
+        "G2 DLEQ",
+        bench_verify::<
+            DLEQSigmaProtocol<G2Affine>,
+            FiatShamir<DLEQSigmaProtocol<G2Affine>>,
+        >,
+    );
     fiatshamir.finish();
 }
 
@@ -0,0 +1,27 @@
+extern crate criterion;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+use ff::{Field, PrimeField};
+use group::{CurveAffine, CurveProjective, Wnaf};
+use pairing::bls12_381::*;
+use pistis::glv;
+use pistis::ro::RO;
+use sha3::Sha3_256;
+
+fn bench_mul(c: &mut Criterion) {
+    let mut rng = Sha3_256::query(b"glv bench").into_rng();
+    let p = G1::random(&mut rng).into_affine();
+    let s = Fr::random(&mut rng);
+    let mut group = c.benchmark_group("G1 mul");
+    group.bench_function("glv", |b| b.iter(|| glv::mul(&p, &s)));
+    group.bench_function("generic", |b| b.iter(|| p.mul(s)));
+    group.bench_function("wnaf", |b| {
+        let mut wnaf = Wnaf::new();
+        b.iter(|| wnaf.scalar(s.into_repr()).base(p.into_projective()))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_mul);
+criterion_main!(benches);
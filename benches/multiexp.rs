@@ -0,0 +1,148 @@
+extern crate criterion;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use ff::{Field, PrimeField, PrimeFieldRepr, ScalarEngine};
+use group::{CurveAffine, CurveProjective};
+use pairing::bls12_381::*;
+use pistis::multiexp::{multiexp, multiexp_batch_affine, Coordinates};
+use pistis::ro::RO;
+use rayon::prelude::*;
+use sha3::Sha3_256;
+
+// Sourced from https://github.com/ebfull/sonic. Licensed under MIT
+// Adapted to introduce parallelism.
+/// The previous `multiexp`, parallel over windows only, with a window size
+/// chosen from the number of points alone, and buckets accumulated in
+/// projective coordinates.
+fn multiexp_per_window<
+    'a,
+    G: CurveAffine,
+    IB: IntoIterator<Item = &'a G>,
+    IS: IntoIterator<Item = &'a G::Scalar>,
+>(
+    g: IB,
+    s: IS,
+) -> G::Projective
+where
+    IB::IntoIter: ExactSizeIterator + Clone + Send,
+    IS::IntoIter: ExactSizeIterator + Clone + Send,
+{
+    let g = g.into_iter();
+    let s = s.into_iter();
+    assert_eq!(g.len(), s.len());
+
+    let c = if s.len() < 32 {
+        3u32
+    } else {
+        (f64::from(s.len() as u32)).ln().ceil() as u32
+    };
+
+    // Convert all of the scalars into representations
+    let s = s.map(|s| s.into_repr()).collect::<Vec<_>>();
+
+    let mask = (1u64 << c) - 1u64;
+    let max_iters = (<G::Engine as ScalarEngine>::Fr::NUM_BITS as f64
+        / c as f64)
+        .ceil() as usize;
+    let windows = (0..max_iters)
+        .map(|i| (i, g.clone(), s.clone()))
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .map(|(cur, g, s)| {
+            let mut acc = G::Projective::zero();
+            let mut buckets = Vec::new();
+
+            buckets.truncate(0);
+            buckets.resize((1 << c) - 1, G::Projective::zero());
+
+            for (&(mut s), g) in s.iter().zip(g) {
+                s.shr(cur as u32 * c);
+                let index = (s.as_ref()[0] & mask) as usize;
+
+                if index != 0 {
+                    buckets[index - 1].add_assign_mixed(g);
+                }
+            }
+
+            let mut running_sum = G::Projective::zero();
+            for exp in buckets.iter().rev() {
+                running_sum.add_assign(exp);
+                acc.add_assign(&running_sum);
+            }
+            acc
+        })
+        .collect::<Vec<_>>();
+
+    let mut acc = G::Projective::zero();
+
+    for window in windows.into_iter().rev() {
+        for _ in 0..c {
+            acc.double();
+        }
+
+        acc.add_assign(&window);
+    }
+
+    acc
+}
+
+/// Generates `n` points and scalars. The points are consecutive multiples of a
+/// random point, which is much cheaper than sampling each at random.
+fn inputs<G: CurveProjective>(n: usize) -> (Vec<G::Affine>, Vec<G::Scalar>) {
+    let mut rng = Sha3_256::query(&[
+        0x2a, 0xb1, 0x74, 0x52, 0x0f, 0x19, 0x34, 0x2a, 0x60, 0x1d, 0xe2, 0x7e,
+        0xa8, 0x97, 0x34, 0xb9,
+    ])
+    .into_rng();
+    let step = G::random(&mut rng);
+    let mut acc = step;
+    let mut points = Vec::with_capacity(n);
+    for _ in 0..n {
+        points.push(acc);
+        acc.add_assign(&step);
+    }
+    G::batch_normalization(&mut points);
+    (
+        points.iter().map(|g| g.into_affine()).collect(),
+        (0..n).map(|_| G::Scalar::random(&mut rng)).collect(),
+    )
+}
+
+fn bench_multiexp<G: CurveProjective>(c: &mut Criterion, name: &str)
+where
+    G::Affine: Coordinates,
+{
+    let mut group = c.benchmark_group(name);
+    for log_n in (10..=20).step_by(2) {
+        let (g, s) = inputs::<G>(1 << log_n);
+        group.bench_with_input(
+            BenchmarkId::new("per window", log_n),
+            &(&g, &s),
+            |b, (g, s)| b.iter(|| multiexp_per_window(g.iter(), s.iter())),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("projective", log_n),
+            &(&g, &s),
+            |b, (g, s)| b.iter(|| multiexp(g.iter(), s.iter())),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("batch affine", log_n),
+            &(&g, &s),
+            |b, (g, s)| b.iter(|| multiexp_batch_affine(g.iter(), s.iter())),
+        );
+    }
+    group.finish();
+}
+
+fn bench_multiexp_all(c: &mut Criterion) {
+    bench_multiexp::<G1>(c, "multiexp G1");
+    bench_multiexp::<G2>(c, "multiexp G2");
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = bench_multiexp_all
+}
+criterion_main!(benches);
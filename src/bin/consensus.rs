@@ -103,13 +103,15 @@ impl Eq for TimeIndex {}
 
 impl PartialOrd for TimeIndex {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        self.1.partial_cmp(&other.1)
+        Some(self.cmp(other))
     }
 }
 
 impl Ord for TimeIndex {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.partial_cmp(other).expect("times must be comparable")
+        self.1
+            .partial_cmp(&other.1)
+            .expect("times must be comparable")
     }
 }
 
@@ -142,15 +144,15 @@ impl Experiment {
                     .enumerate()
                     .map(|(i, s)| TimeIndex(i, s.next_adv)),
             )
-            .map(|i| Reverse(i))
+            .map(Reverse)
             .collect();
         Experiment {
-            states: states,
+            states,
             data: vec![(0f64, 0f64)],
             params: p,
             nwinning: 0,
-            queue: queue,
-            granularity: granularity,
+            queue,
+            granularity,
             next_recorded: granularity,
         }
     }
@@ -197,7 +199,8 @@ impl Experiment {
         self
     }
 
-    /// Returns the first recorded point (if any) which satisfies the given confidence level.
+    /// Returns the first recorded point (if any) which satisfies the given
+    /// confidence level.
     fn conf(&self, conf: f64) -> Option<f64> {
         let pos = match self.data.binary_search_by(|(_, y)| {
             y.partial_cmp(&conf)
@@ -221,7 +224,7 @@ impl Experiment {
         rng: &mut R,
     ) -> Option<f64> {
         let mut t = 0f64;
-        while self.data.len() == 0 || self.data[self.data.len() - 1].1 < conf {
+        while self.data.is_empty() || self.data[self.data.len() - 1].1 < conf {
             t = self.step(rng);
             if t > limit {
                 return None;
@@ -244,7 +247,7 @@ pub fn main() {
     .for_each(|(h, len, step)| {
         (0..=10)
             .into_par_iter()
-            .map(|i| (i as f64 * step))
+            .map(|i| i as f64 * step)
             .for_each(|d| {
                 Experiment::new(
                     ConsensusParameters::new(d, 1.0, h, &mut rng()),
@@ -259,10 +262,10 @@ pub fn main() {
             });
     });
 
-    // For each inter-block time, how long a (phase 1) bootstrap is needed (with 55% honest, 99.9%
-    // confidence)
+    // For each inter-block time, how long a (phase 1) bootstrap is needed (with
+    // 55% honest, 99.9% confidence)
     [0.55, 0.67, 0.9].par_iter().for_each(|h| {
-        //for c in [0.999, 0.9999, 0.99999].iter() {
+        // for c in [0.999, 0.9999, 0.99999].iter() {
         let confs = [0.999, 0.9999, 0.99999];
         let data = (1..=250)
             .into_par_iter()
@@ -1,8 +1,10 @@
 use pairing::bls12_381::*;
 use pistis::poe::*;
 use pistis::ro::RO;
+use pistis::serialize::Serializable;
 use pistis::usrs::*;
-use rand::Rng;
+use pistis::util::Split;
+use rand::{CryptoRng, Rng};
 use sha3::Sha3_256;
 use std::env::args;
 use std::fs::{create_dir_all, File, OpenOptions};
@@ -10,7 +12,7 @@ use std::io::{stdout, Write};
 use std::path::Path;
 use std::time::Instant;
 
-const DS: &'static [usize] = &[
+const DS: &[usize] = &[
     0x00_00_02, 0x00_00_04, 0x00_00_08, 0x00_00_10, 0x00_00_20, 0x00_00_40,
     0x00_00_80, 0x00_01_00, 0x00_02_00, 0x00_04_00, 0x00_08_00, 0x00_10_00,
     0x00_20_00, 0x00_40_00, 0x00_80_00, 0x01_00_00, 0x02_00_00, 0x04_00_00,
@@ -22,6 +24,45 @@ const TRIALS_LARGE: usize = 10;
 
 type Fischlin =
     FischlinTransform<DualProofOfExponentSigmaProtocol<G1Affine>, Sha3_256>;
+type LinkedFischlin = FischlinTransform<LinkSigmaProtocol<Bls12>, Sha3_256>;
+type FiatShamir =
+    FiatShamirTransform<DualProofOfExponentSigmaProtocol<G1Affine>, Sha3_256>;
+type LinkedFiatShamir = FiatShamirTransform<LinkSigmaProtocol<Bls12>, Sha3_256>;
+
+/// The encoded size of `upd`, less that of the SRS it carries, which has the
+/// size of `srs`.
+fn proof_size<U: Serializable>(upd: &U, srs: &USRS<Bls12>) -> usize {
+    let mut upd_bytes = Vec::new();
+    upd.write(&mut upd_bytes).unwrap();
+    let mut srs_bytes = Vec::new();
+    srs.write(&mut srs_bytes).unwrap();
+    upd_bytes.len() - srs_bytes.len()
+}
+
+/// The average times, in milliseconds, to create and to verify an update to
+/// each of `srss`.
+fn time_update<P, R>(srss: &[USRS<Bls12>], rng: &mut R) -> (u128, u128)
+where
+    P: UpdateProof<Bls12>,
+    R: Split + Rng + CryptoRng + Send,
+{
+    let (tu, tv): (Vec<_>, Vec<_>) = srss
+        .iter()
+        .map(|srs| {
+            let t0 = Instant::now();
+            let upd = Update::<Bls12, P>::new(srs, rng);
+            let tu = t0.elapsed().as_millis();
+            let t0 = Instant::now();
+            assert!(upd.verify(srs, rng));
+            (tu, t0.elapsed().as_millis())
+        })
+        .unzip();
+    let trials = srss.len() as u128;
+    (
+        tu.into_iter().sum::<u128>() / trials,
+        tv.into_iter().sum::<u128>() / trials,
+    )
+}
 
 fn file<P: AsRef<Path>>(path: P) -> File {
     OpenOptions::new()
@@ -32,7 +73,7 @@ fn file<P: AsRef<Path>>(path: P) -> File {
 }
 
 fn main() {
-    let mut skip = match args().skip(1).next().map(|arg| arg.parse::<usize>()) {
+    let mut skip = match args().nth(1).map(|arg| arg.parse::<usize>()) {
         Some(Ok(skip)) => skip,
         _ => 0,
     };
@@ -42,9 +83,36 @@ fn main() {
     ])
     .into_rng();
     create_dir_all("data").unwrap();
+    let mut permute_data = file("data/permute.csv");
     let mut prove_data = file("data/prove.csv");
     let mut verify_data = file("data/verify.csv");
     let mut agg_data = file("data/agg_verify.csv");
+    let mut linked_prove_data = file("data/prove_linked.csv");
+    let mut linked_verify_data = file("data/verify_linked.csv");
+    let mut fs_prove_data = file("data/prove_fs.csv");
+    let mut fs_verify_data = file("data/verify_fs.csv");
+    let mut linked_fs_prove_data = file("data/prove_linked_fs.csv");
+    let mut linked_fs_verify_data = file("data/verify_linked_fs.csv");
+    // Compare the sizes of the update proofs in each mode, which do not depend
+    // on d
+    {
+        let srs = USRS::<Bls12>::new(2);
+        let upd = Update::<Bls12, Fischlin>::new(&srs, &mut rng);
+        let linked = LinkedUpdate::<Bls12, LinkedFischlin>::new(&srs, &mut rng);
+        let fs = Update::<Bls12, FiatShamir>::new(&srs, &mut rng);
+        let linked_fs =
+            LinkedUpdate::<Bls12, LinkedFiatShamir>::new(&srs, &mut rng);
+        let mut size_data = file("data/proof_size.csv");
+        writeln!(
+            &mut size_data,
+            "{},{},{},{}",
+            proof_size(&upd, &srs),
+            proof_size(&linked, &srs),
+            proof_size(&fs, &srs),
+            proof_size(&linked_fs, &srs)
+        )
+        .unwrap();
+    }
     // Test plain updates/verifies
     for (i, d) in DS.iter().enumerate().skip(skip) {
         let trials = if *d < 0x01_00_00 {
@@ -54,16 +122,22 @@ fn main() {
         };
         print!("{} - {}/", i, d);
         stdout().flush().unwrap();
-        let srss = (0..trials)
-            .map(|_| USRS::new(*d).permute(&rng.gen()))
-            .collect::<Vec<_>>();
+        let (tp, srss): (Vec<_>, Vec<_>) = (0..trials)
+            .map(|_| {
+                let t0 = Instant::now();
+                let srs = USRS::new(*d).permute(&rng.gen());
+                (t0.elapsed().as_millis(), srs)
+            })
+            .unzip();
+        let avg_tp: u128 = tp.into_iter().sum::<u128>() / trials as u128;
+        writeln!(&mut permute_data, "{},{}", d, avg_tp).unwrap();
         print!("S");
         stdout().flush().unwrap();
         let tu_upd = srss
             .iter()
             .map(|srs| {
                 let t0 = Instant::now();
-                let upd = Update::<Bls12, Fischlin>::new(&srs, &mut rng);
+                let upd = Update::<Bls12, Fischlin>::new(srs, &mut rng);
                 let tu = t0.elapsed().as_millis();
                 (tu, upd)
             })
@@ -74,7 +148,7 @@ fn main() {
             .zip(tu_upd.iter())
             .map(|(srs, (_, upd))| {
                 let t0 = Instant::now();
-                assert!(upd.verify(&srs, &mut rng));
+                assert!(upd.verify(srs, &mut rng));
                 t0.elapsed().as_millis()
             })
             .collect::<Vec<_>>();
@@ -83,7 +157,24 @@ fn main() {
         let avg_tv: u128 = tv.into_iter().sum::<u128>() / trials as u128;
         writeln!(&mut prove_data, "{},{}", d, avg_tu).unwrap();
         writeln!(&mut verify_data, "{},{}", d, avg_tv).unwrap();
-        println!("V");
+        print!("V");
+        stdout().flush().unwrap();
+        let (tu, tv) =
+            time_update::<Linked<LinkedFischlin>, _>(&srss, &mut rng);
+        writeln!(&mut linked_prove_data, "{},{}", d, tu).unwrap();
+        writeln!(&mut linked_verify_data, "{},{}", d, tv).unwrap();
+        print!("L");
+        stdout().flush().unwrap();
+        let (tu, tv) = time_update::<FiatShamir, _>(&srss, &mut rng);
+        writeln!(&mut fs_prove_data, "{},{}", d, tu).unwrap();
+        writeln!(&mut fs_verify_data, "{},{}", d, tv).unwrap();
+        print!("F");
+        stdout().flush().unwrap();
+        let (tu, tv) =
+            time_update::<Linked<LinkedFiatShamir>, _>(&srss, &mut rng);
+        writeln!(&mut linked_fs_prove_data, "{},{}", d, tu).unwrap();
+        writeln!(&mut linked_fs_verify_data, "{},{}", d, tv).unwrap();
+        println!("LF");
     }
     if skip > DS.len() {
         skip -= DS.len();
@@ -0,0 +1,430 @@
+//! AND and OR compositions of sigma protocols.
+//!
+//! [`AndComposition`] proves both statements, answering one challenge in both
+//! protocols. [`OrComposition`] proves either statement without revealing
+//! which, following Cramer, Damgård and Schoenmakers [1]: the prover simulates
+//! the branch it has no witness for, and splits the challenge so that it can
+//! answer the other branch honestly.
+//!
+//! Both require the two protocols to share a challenge type. Statements over
+//! [`LinearSigmaProtocol`] always do, so that is a convenient way to combine
+//! different kinds of statement. The compositions are themselves simulatable
+//! sigma protocols, so they can be nested. An AND composition can be used with
+//! either transform. An OR composition can only be used with the
+//! [`FiatShamirTransform`], as its responses are not unique, which
+//! [`FischlinTransform`] requires.
+//!
+//! [1] Ronald Cramer, Ivan Damgård and Berry Schoenmakers. Proofs of partial
+//! knowledge and simplified design of witness hiding protocols. CRYPTO 1994.
+//!
+//! [`AndComposition`]: struct.AndComposition.html
+//! [`OrComposition`]: struct.OrComposition.html
+//! [`LinearSigmaProtocol`]: ../linear/struct.LinearSigmaProtocol.html
+//! [`FiatShamirTransform`]: ../poe/struct.FiatShamirTransform.html
+//! [`FischlinTransform`]: ../poe/struct.FischlinTransform.html
+
+use crate::poe::{
+    ChallengeGroup, Relation, SigmaProtocol, SimulatableSigmaProtocol,
+    UniqueResponses,
+};
+use crate::serialize::Serializable;
+use crate::usrs::ImportError;
+use rand::distributions::{Distribution, Standard};
+use rand::{CryptoRng, Rng};
+use std::io::{self, Read, Write};
+use std::marker::PhantomData;
+
+/// Proves both `S1` and `S2`.
+pub struct AndComposition<S1, S2>(PhantomData<(S1, S2)>);
+
+/// Proves either `S1` or `S2`, without revealing which.
+///
+/// This does not have [`UniqueResponses`], so it can only be made
+/// non-interactive with the [`FiatShamirTransform`].
+///
+/// [`UniqueResponses`]: ../poe/trait.UniqueResponses.html
+/// [`FiatShamirTransform`]: ../poe/struct.FiatShamirTransform.html
+pub struct OrComposition<S1, S2>(PhantomData<(S1, S2)>);
+
+#[derive(Clone, PartialEq, Eq)]
+/// A pair of protocol values, such as statements or commitments
+pub struct Composed<A, B>(pub A, pub B, Vec<u8>);
+
+impl<A: AsRef<[u8]>, B: AsRef<[u8]>> Composed<A, B> {
+    pub fn new(a: A, b: B) -> Self {
+        // Prefix the first encoding with its length, so the pair's encoding is
+        // unambiguous.
+        let mut vec = Vec::new();
+        vec.extend((a.as_ref().len() as u64).to_le_bytes().iter());
+        vec.extend(a.as_ref().iter());
+        vec.extend(b.as_ref().iter());
+        Composed(a, b, vec)
+    }
+}
+
+impl<A, B> AsRef<[u8]> for Composed<A, B> {
+    fn as_ref(&self) -> &[u8] {
+        self.2.as_ref()
+    }
+}
+
+impl<A: AsRef<[u8]> + Serializable, B: AsRef<[u8]> + Serializable> Serializable
+    for Composed<A, B>
+{
+    fn write<W: Write>(&self, mut out: W) -> io::Result<()> {
+        self.0.write(&mut out)?;
+        self.1.write(&mut out)
+    }
+
+    fn read<R: Read>(mut inp: R) -> Result<Self, ImportError> {
+        Ok(Composed::new(A::read(&mut inp)?, B::read(&mut inp)?))
+    }
+}
+
+/// A witness for one side of an [`OrComposition`].
+///
+/// [`OrComposition`]: struct.OrComposition.html
+pub enum OrWitness<A, B> {
+    Left(A),
+    Right(B),
+}
+
+impl<A: AsRef<[u8]>, B: AsRef<[u8]>> AsRef<[u8]> for OrWitness<A, B> {
+    fn as_ref(&self) -> &[u8] {
+        match self {
+            OrWitness::Left(a) => a.as_ref(),
+            OrWitness::Right(b) => b.as_ref(),
+        }
+    }
+}
+
+/// The prover's state in an [`OrComposition`]: its first step state for the
+/// branch it knows a witness for, and the simulated challenge and response for
+/// the other.
+///
+/// [`OrComposition`]: struct.OrComposition.html
+pub enum OrState<S1: SigmaProtocol, S2: SigmaProtocol> {
+    Left(S1::Z, S1::C, S2::R),
+    Right(S2::Z, S1::C, S1::R),
+}
+
+#[derive(Clone)]
+/// The response of an [`OrComposition`]: the first branch's share of the
+/// challenge, and both branches' responses. The second branch's share is the
+/// rest of the challenge.
+///
+/// [`OrComposition`]: struct.OrComposition.html
+pub struct OrResponse<C, R1, R2> {
+    pub c1: C,
+    pub r1: R1,
+    pub r2: R2,
+    bytes: Vec<u8>,
+}
+
+impl<C: AsRef<[u8]>, R1: AsRef<[u8]>, R2: AsRef<[u8]>> OrResponse<C, R1, R2> {
+    pub fn new(c1: C, r1: R1, r2: R2) -> Self {
+        let mut bytes = Vec::new();
+        for part in [c1.as_ref(), r1.as_ref()].iter() {
+            bytes.extend((part.len() as u64).to_le_bytes().iter());
+            bytes.extend(part.iter());
+        }
+        bytes.extend(r2.as_ref().iter());
+        OrResponse { c1, r1, r2, bytes }
+    }
+}
+
+impl<C, R1, R2> AsRef<[u8]> for OrResponse<C, R1, R2> {
+    fn as_ref(&self) -> &[u8] {
+        self.bytes.as_ref()
+    }
+}
+
+impl<
+        C: AsRef<[u8]> + Serializable,
+        R1: AsRef<[u8]> + Serializable,
+        R2: AsRef<[u8]> + Serializable,
+    > Serializable for OrResponse<C, R1, R2>
+{
+    fn write<W: Write>(&self, mut out: W) -> io::Result<()> {
+        self.c1.write(&mut out)?;
+        self.r1.write(&mut out)?;
+        self.r2.write(&mut out)
+    }
+
+    fn read<R: Read>(mut inp: R) -> Result<Self, ImportError> {
+        Ok(OrResponse::new(
+            C::read(&mut inp)?,
+            R1::read(&mut inp)?,
+            R2::read(&mut inp)?,
+        ))
+    }
+}
+
+impl<S1: SigmaProtocol, S2: SigmaProtocol> Relation for AndComposition<S1, S2> {
+    type X = Composed<S1::X, S2::X>;
+    type W = Composed<S1::W, S2::W>;
+
+    fn check(x: &Self::X, w: &Self::W) -> bool {
+        S1::check(&x.0, &w.0) && S2::check(&x.1, &w.1)
+    }
+}
+
+impl<S1: SigmaProtocol, S2: SigmaProtocol<C = S1::C>> SigmaProtocol
+    for AndComposition<S1, S2>
+where
+    S1::T: AsRef<[u8]>,
+    S2::T: AsRef<[u8]>,
+    S1::R: AsRef<[u8]>,
+    S2::R: AsRef<[u8]>,
+{
+    type Z = (S1::Z, S2::Z);
+    type T = Composed<S1::T, S2::T>;
+    type C = S1::C;
+    type R = Composed<S1::R, S2::R>;
+
+    fn prove_step_1<R: Rng + CryptoRng + ?Sized>(
+        x: &Self::X,
+        w: &Self::W,
+        rng: &mut R,
+    ) -> (Self::Z, Self::T) {
+        let (z1, t1) = S1::prove_step_1(&x.0, &w.0, rng);
+        let (z2, t2) = S2::prove_step_1(&x.1, &w.1, rng);
+        ((z1, z2), Composed::new(t1, t2))
+    }
+
+    fn prove_step_2(
+        x: &Self::X,
+        w: &Self::W,
+        (z1, z2): &Self::Z,
+        c: &Self::C,
+    ) -> Self::R {
+        Composed::new(
+            S1::prove_step_2(&x.0, &w.0, z1, c),
+            S2::prove_step_2(&x.1, &w.1, z2, c),
+        )
+    }
+
+    fn finish_verify(
+        x: &Self::X,
+        t: &Self::T,
+        c: &Self::C,
+        r: &Self::R,
+    ) -> bool {
+        S1::finish_verify(&x.0, &t.0, c, &r.0)
+            && S2::finish_verify(&x.1, &t.1, c, &r.1)
+    }
+}
+
+/// Both responses are unique, for the same challenge.
+impl<S1: UniqueResponses, S2: UniqueResponses<C = S1::C>> UniqueResponses
+    for AndComposition<S1, S2>
+where
+    S1::T: AsRef<[u8]>,
+    S2::T: AsRef<[u8]>,
+    S1::R: AsRef<[u8]>,
+    S2::R: AsRef<[u8]>,
+{
+}
+
+impl<S1: SimulatableSigmaProtocol, S2: SimulatableSigmaProtocol<C = S1::C>>
+    SimulatableSigmaProtocol for AndComposition<S1, S2>
+where
+    S1::T: AsRef<[u8]>,
+    S2::T: AsRef<[u8]>,
+    S1::R: AsRef<[u8]>,
+    S2::R: AsRef<[u8]>,
+{
+    fn simulate<R: Rng + CryptoRng + ?Sized>(
+        x: &Self::X,
+        c: &Self::C,
+        rng: &mut R,
+    ) -> (Self::T, Self::R) {
+        let (t1, r1) = S1::simulate(&x.0, c, rng);
+        let (t2, r2) = S2::simulate(&x.1, c, rng);
+        (Composed::new(t1, t2), Composed::new(r1, r2))
+    }
+}
+
+impl<S1: SigmaProtocol, S2: SigmaProtocol> Relation for OrComposition<S1, S2> {
+    type X = Composed<S1::X, S2::X>;
+    type W = OrWitness<S1::W, S2::W>;
+
+    fn check(x: &Self::X, w: &Self::W) -> bool {
+        match w {
+            OrWitness::Left(w) => S1::check(&x.0, w),
+            OrWitness::Right(w) => S2::check(&x.1, w),
+        }
+    }
+}
+
+impl<S1: SimulatableSigmaProtocol, S2: SimulatableSigmaProtocol<C = S1::C>>
+    SigmaProtocol for OrComposition<S1, S2>
+where
+    Standard: Distribution<S1::C>,
+    S1::C: ChallengeGroup + AsRef<[u8]> + Clone,
+    S1::T: AsRef<[u8]>,
+    S2::T: AsRef<[u8]>,
+    S1::R: AsRef<[u8]> + Clone,
+    S2::R: AsRef<[u8]> + Clone,
+{
+    type Z = OrState<S1, S2>;
+    type T = Composed<S1::T, S2::T>;
+    type C = S1::C;
+    type R = OrResponse<S1::C, S1::R, S2::R>;
+
+    fn prove_step_1<R: Rng + CryptoRng + ?Sized>(
+        x: &Self::X,
+        w: &Self::W,
+        rng: &mut R,
+    ) -> (Self::Z, Self::T) {
+        let c_sim: S1::C = rng.gen();
+        match w {
+            OrWitness::Left(w) => {
+                let (z1, t1) = S1::prove_step_1(&x.0, w, rng);
+                let (t2, r2) = S2::simulate(&x.1, &c_sim, rng);
+                (OrState::Left(z1, c_sim, r2), Composed::new(t1, t2))
+            }
+            OrWitness::Right(w) => {
+                let (t1, r1) = S1::simulate(&x.0, &c_sim, rng);
+                let (z2, t2) = S2::prove_step_1(&x.1, w, rng);
+                (OrState::Right(z2, c_sim, r1), Composed::new(t1, t2))
+            }
+        }
+    }
+
+    fn prove_step_2(
+        x: &Self::X,
+        w: &Self::W,
+        z: &Self::Z,
+        c: &Self::C,
+    ) -> Self::R {
+        match (w, z) {
+            (OrWitness::Left(w), OrState::Left(z1, c2, r2)) => {
+                let c1 = c.sub(c2);
+                let r1 = S1::prove_step_2(&x.0, w, z1, &c1);
+                OrResponse::new(c1, r1, r2.clone())
+            }
+            (OrWitness::Right(w), OrState::Right(z2, c1, r1)) => {
+                let c2 = c.sub(c1);
+                let r2 = S2::prove_step_2(&x.1, w, z2, &c2);
+                OrResponse::new(c1.clone(), r1.clone(), r2)
+            }
+            _ => panic!("the prover state does not match the witness"),
+        }
+    }
+
+    fn finish_verify(
+        x: &Self::X,
+        t: &Self::T,
+        c: &Self::C,
+        r: &Self::R,
+    ) -> bool {
+        let c2 = c.sub(&r.c1);
+        S1::finish_verify(&x.0, &t.0, &r.c1, &r.r1)
+            && S2::finish_verify(&x.1, &t.1, &c2, &r.r2)
+    }
+}
+
+impl<S1: SimulatableSigmaProtocol, S2: SimulatableSigmaProtocol<C = S1::C>>
+    SimulatableSigmaProtocol for OrComposition<S1, S2>
+where
+    Standard: Distribution<S1::C>,
+    S1::C: ChallengeGroup + AsRef<[u8]> + Clone,
+    S1::T: AsRef<[u8]>,
+    S2::T: AsRef<[u8]>,
+    S1::R: AsRef<[u8]> + Clone,
+    S2::R: AsRef<[u8]> + Clone,
+{
+    fn simulate<R: Rng + CryptoRng + ?Sized>(
+        x: &Self::X,
+        c: &Self::C,
+        rng: &mut R,
+    ) -> (Self::T, Self::R) {
+        let c1: S1::C = rng.gen();
+        let c2 = c.sub(&c1);
+        let (t1, r1) = S1::simulate(&x.0, &c1, rng);
+        let (t2, r2) = S2::simulate(&x.1, &c2, rng);
+        (Composed::new(t1, t2), OrResponse::new(c1, r1, r2))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poe::{
+        CurvePoint, FiatShamirTransform, FieldElement, FischlinTransform,
+        SchnorrSigmaProtocol, NIZK,
+    };
+    use crate::ro::RO;
+    use ff::Field;
+    use group::{CurveAffine, CurveProjective};
+    use pairing::bls12_381::{Fr, G1Affine};
+    use sha3::Sha3_256;
+
+    type Schnorr = SchnorrSigmaProtocol<G1Affine>;
+    type Or = FiatShamirTransform<OrComposition<Schnorr, Schnorr>, Sha3_256>;
+    type And = FiatShamirTransform<AndComposition<Schnorr, Schnorr>, Sha3_256>;
+    type AndFischlin =
+        FischlinTransform<AndComposition<Schnorr, Schnorr>, Sha3_256>;
+
+    /// Two Schnorr statements, and their witnesses.
+    fn instances() -> (
+        Composed<CurvePoint<G1Affine>, CurvePoint<G1Affine>>,
+        [Fr; 2],
+    ) {
+        let mut rng = Sha3_256::query(b"compose test").into_rng();
+        let w = [Fr::random(&mut rng), Fr::random(&mut rng)];
+        let x = |w: Fr| CurvePoint::new(G1Affine::one().mul(w).into_affine());
+        (Composed::new(x(w[0]), x(w[1])), w)
+    }
+
+    #[test]
+    fn or_proves_from_either_branch() {
+        let mut rng = Sha3_256::query(b"compose or").into_rng();
+        let (x, w) = instances();
+        let witnesses = [
+            OrWitness::Left(FieldElement::new(w[0])),
+            OrWitness::Right(FieldElement::new(w[1])),
+        ];
+        for w in witnesses.iter() {
+            let (t, r) = Or::prove(&x, w, &mut rng);
+            assert!(Or::verify(&x, &(t.clone(), r.clone())));
+            // Moving part of the challenge from one branch to the other is
+            // rejected.
+            let mut c1 = r.c1.0;
+            c1.add_assign(&Fr::one());
+            let split = OrResponse::new(FieldElement::new(c1), r.r1, r.r2);
+            assert!(!Or::verify(&x, &(t, split)));
+        }
+        let wrong = OrWitness::Left(FieldElement::new(w[1]));
+        assert!(!Or::check(&x, &wrong));
+    }
+
+    #[test]
+    fn and_needs_both_witnesses() {
+        let mut rng = Sha3_256::query(b"compose and").into_rng();
+        let (x, w) = instances();
+        let w = Composed::new(FieldElement::new(w[0]), FieldElement::new(w[1]));
+        let pi = And::prove(&x, &w, &mut rng);
+        assert!(And::verify(&x, &pi));
+        let swapped = Composed::new(x.1.clone(), x.0.clone());
+        assert!(!And::verify(&swapped, &pi));
+        assert!(!And::check(&swapped, &w));
+    }
+
+    #[test]
+    fn and_composes_under_fischlin() {
+        // Responses of the composition are unique as each branch's are, so it
+        // may be used with the straight-line extractable transform.
+        let mut rng = Sha3_256::query(b"compose and fischlin").into_rng();
+        let (x, w) = instances();
+        let w = Composed::new(FieldElement::new(w[0]), FieldElement::new(w[1]));
+        let pi = AndFischlin::prove(&x, &w, &mut rng);
+        assert!(AndFischlin::verify(&x, &pi));
+        let swapped = Composed::new(x.1.clone(), x.0.clone());
+        assert!(!AndFischlin::verify(&swapped, &pi));
+        let mut truncated = pi;
+        truncated.pop();
+        assert!(!AndFischlin::verify(&x, &truncated));
+    }
+}
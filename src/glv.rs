@@ -0,0 +1,198 @@
+//! GLV scalar multiplication for BLS12-381's G1.
+//!
+//! G1 has the efficiently computable endomorphism `\phi(x, y) = (\beta x, y)`,
+//! for `\beta` a cube root of unity in the base field, which acts on the prime
+//! order subgroup as multiplication by `\lambda = z^2 - 1`, for `z` the curve
+//! parameter. As `r = \lambda^2 + \lambda + 1`, every scalar `k` splits as
+//! `k_1 + k_2 \lambda` with both halves below `2^{128}`, and
+//! `k P = k_1 P + k_2 \phi(P)` needs only half as many doublings.
+//!
+//! The coordinates of a point are not exposed by `pairing`, so the endomorphism
+//! goes through the point's uncompressed encoding. This costs a few field
+//! multiplications, which is negligible next to a scalar multiplication.
+//!
+//! Nothing else in the crate uses this module, which is only built with the
+//! `glv` feature; callers multiplying G1 points call [`mul`] directly.
+//!
+//! [`mul`]: fn.mul.html
+
+use ff::{Field, PrimeField, PrimeFieldRepr, SqrtField};
+use group::{CurveAffine, CurveProjective, EncodedPoint};
+use pairing::bls12_381::{Fq, FqRepr, Fr, FrRepr, G1Affine, G1};
+use std::sync::OnceLock;
+
+/// The absolute value of the BLS12-381 curve parameter `z`.
+const Z: u128 = 0xd201_0000_0001_0000;
+
+/// The eigenvalue of the endomorphism, `z^2 - 1`.
+const LAMBDA: u128 = Z * Z - 1;
+
+/// The width of the non-adjacent forms of the scalar halves.
+const WINDOW: usize = 5;
+
+/// The size of an encoded base field element.
+const FQ_SIZE: usize = 48;
+
+/// The cube root of unity `\beta` for which `\phi(P) = \lambda P`.
+fn beta() -> Fq {
+    static BETA: OnceLock<Fq> = OnceLock::new();
+    *BETA.get_or_init(|| {
+        // The roots of x^2 + x + 1 are (-1 \pm \sqrt{-3}) / 2; pick the one
+        // matching lambda.
+        let mut minus_three = Fq::from_repr(FqRepr::from(3)).unwrap();
+        minus_three.negate();
+        let mut beta = minus_three
+            .sqrt()
+            .expect("-3 is a square, as 3 divides q - 1");
+        beta.sub_assign(&Fq::one());
+        beta.mul_assign(
+            &Fq::from_repr(FqRepr::from(2)).unwrap().inverse().unwrap(),
+        );
+        let lambda = FrRepr([LAMBDA as u64, (LAMBDA >> 64) as u64, 0, 0]);
+        let expected = G1Affine::one().mul(lambda).into_affine();
+        if apply(&G1Affine::one(), &beta) != expected {
+            beta.square();
+            assert_eq!(apply(&G1Affine::one(), &beta), expected);
+        }
+        beta
+    })
+}
+
+/// Computes `(\beta x, y)` for `p = (x, y)`.
+fn apply(p: &G1Affine, beta: &Fq) -> G1Affine {
+    if p.is_zero() {
+        return *p;
+    }
+    // Neither flag bit is set for an uncompressed finite point.
+    let mut enc = p.into_uncompressed();
+    let mut repr = FqRepr::default();
+    repr.read_be(&enc.as_ref()[..FQ_SIZE])
+        .expect("the encoding holds a full coordinate");
+    let mut x = Fq::from_repr(repr).expect("the coordinate is reduced");
+    x.mul_assign(beta);
+    x.into_repr()
+        .write_be(&mut enc.as_mut()[..FQ_SIZE])
+        .expect("the encoding holds a full coordinate");
+    enc.into_affine_unchecked()
+        .expect("the endomorphism maps the curve to itself")
+}
+
+/// Computes the endomorphism `\phi(p) = \lambda p`.
+pub fn endomorphism(p: &G1Affine) -> G1Affine {
+    apply(p, &beta())
+}
+
+/// Splits `k` into `(k_1, k_2)` with `k = k_1 + k_2 \lambda`.
+///
+/// This is simply division with remainder by `\lambda`: as
+/// `k < r < (\lambda + 1)^2`, the quotient is at most `\lambda`.
+fn decompose(k: &FrRepr) -> (u128, u128) {
+    let (mut quot, mut rem) = (0u128, 0u128);
+    for i in (0..256).rev() {
+        let bit = ((k.0[i / 64] >> (i % 64)) & 1) as u128;
+        // The remainder is below lambda, so doubling it overflows at most once.
+        let overflow = rem >> 127 == 1;
+        rem = rem << 1 | bit;
+        quot <<= 1;
+        if overflow || rem >= LAMBDA {
+            rem = rem.wrapping_sub(LAMBDA);
+            quot |= 1;
+        }
+    }
+    (rem, quot)
+}
+
+/// The width-`WINDOW` non-adjacent form of `k`, least significant digit first.
+fn wnaf(mut k: u128) -> Vec<i8> {
+    let mut res = Vec::with_capacity(130);
+    while k != 0 {
+        if k & 1 == 1 {
+            let mut digit = (k & ((1 << WINDOW) - 1)) as i8;
+            if digit >= 1 << (WINDOW - 1) {
+                digit -= 1 << WINDOW;
+            }
+            // Both halves are below lambda, well clear of overflow.
+            if digit > 0 {
+                k -= digit as u128;
+            } else {
+                k += (-digit) as u128;
+            }
+            res.push(digit);
+        } else {
+            res.push(0);
+        }
+        k >>= 1;
+    }
+    res
+}
+
+/// The odd multiples `p, 3p, ..., (2^{WINDOW - 1} - 1) p`.
+fn odd_multiples(p: &G1Affine) -> Vec<G1Affine> {
+    let mut double = p.into_projective();
+    double.double();
+    let mut res = vec![p.into_projective()];
+    for i in 1..1 << (WINDOW - 2) {
+        let mut next = res[i - 1];
+        next.add_assign(&double);
+        res.push(next);
+    }
+    G1::batch_normalization(&mut res);
+    res.iter().map(|p| p.into_affine()).collect()
+}
+
+/// Adds `digit` times the point whose odd multiples are `table` to `acc`.
+fn add_digit(acc: &mut G1, digit: i8, table: &[G1Affine]) {
+    if digit > 0 {
+        acc.add_assign_mixed(&table[(digit as usize) / 2]);
+    } else if digit < 0 {
+        let mut p = table[(-digit as usize) / 2];
+        p.negate();
+        acc.add_assign_mixed(&p);
+    }
+}
+
+/// Computes `p^s` using the GLV decomposition.
+///
+/// Agrees with `p.mul(s)`.
+pub fn mul(p: &G1Affine, s: &Fr) -> G1 {
+    let (k1, k2) = decompose(&s.into_repr());
+    let t1 = odd_multiples(p);
+    let t2 = t1.iter().map(endomorphism).collect::<Vec<_>>();
+    let (n1, n2) = (wnaf(k1), wnaf(k2));
+    let mut acc = G1::zero();
+    for i in (0..n1.len().max(n2.len())).rev() {
+        acc.double();
+        add_digit(&mut acc, n1.get(i).copied().unwrap_or(0), &t1);
+        add_digit(&mut acc, n2.get(i).copied().unwrap_or(0), &t2);
+    }
+    acc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ro::RO;
+    use sha3::Sha3_256;
+
+    #[test]
+    fn mul_matches_generic_path() {
+        let mut rng = Sha3_256::query(b"glv test").into_rng();
+        let lambda =
+            Fr::from_repr(FrRepr([LAMBDA as u64, (LAMBDA >> 64) as u64, 0, 0]))
+                .unwrap();
+        let mut minus_one = Fr::one();
+        minus_one.negate();
+        let mut scalars = vec![Fr::zero(), Fr::one(), lambda, minus_one];
+        scalars.extend((0..16).map(|_| Fr::random(&mut rng)));
+        let points = [G1Affine::one(), G1::random(&mut rng).into_affine()];
+        for p in points.iter() {
+            for s in scalars.iter() {
+                let mut expected = p.into_projective();
+                expected.mul_assign(*s);
+                assert_eq!(mul(p, s), expected);
+            }
+            assert_eq!(endomorphism(p), p.mul(lambda).into_affine());
+        }
+        assert_eq!(mul(&G1Affine::zero(), &scalars[4]), G1::zero());
+    }
+}
@@ -0,0 +1,344 @@
+//! A "powers of tau" reference string for KZG-based proving systems, such as
+//! PLONK or Marlin.
+//!
+//! These only need `g^{x^i}` for nonnegative `i`, and `h^x`, all of which are
+//! contained in Sonic's [`USRS`]. A single ceremony can therefore serve both.
+//!
+//! [`USRS`]: ../usrs/struct.USRS.html
+
+use crate::multiexp::multiexp;
+use crate::usrs::{
+    curve_id, points_digest, read_points_file, write_points_file, ImportError,
+    SRSHeader, StructureCheck, VerificationError, USRS,
+};
+use crate::util::random_scalars;
+use ff::Field;
+use group::{CurveAffine, CurveProjective};
+use pairing::{Engine, PairingCurveAffine};
+use rand::{CryptoRng, Rng};
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+/// Magic bytes opening a powers of tau file.
+pub const POT_MAGIC: [u8; 8] = *b"PISTPOT\0";
+/// The powers of tau file format version written by this library.
+pub const POT_VERSION: u16 = 1;
+
+/// A reference string of the powers `g^{x^i}` for `i` from 0 to `n`, along with
+/// `h` and `h^x`.
+#[derive(Clone)]
+pub struct PowersOfTau<E: Engine> {
+    /// The maximum degree supported.
+    pub n: usize,
+    /// g^{x^i} for i \in 0 to n
+    pub g_x: Vec<E::G1Affine>,
+    /// h
+    pub h: E::G2Affine,
+    /// h^x
+    pub h_x: E::G2Affine,
+}
+
+/// An error extracting powers of tau from a [`USRS`].
+///
+/// [`USRS`]: ../usrs/struct.USRS.html
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExtractionError {
+    /// The maximum degree is zero, or exceeds the dimension of the SRS.
+    Degree {
+        /// The requested maximum degree.
+        n: usize,
+        /// The dimension of the SRS.
+        d: usize,
+    },
+}
+
+impl fmt::Display for ExtractionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExtractionError::Degree { n, d } => write!(
+                f,
+                "degree {} is not between 1 and the SRS dimension {}",
+                n, d
+            ),
+        }
+    }
+}
+
+impl Error for ExtractionError {}
+
+impl<E: Engine> USRS<E> {
+    /// Extracts the powers of tau up to degree `n`, which must be between 1
+    /// and the dimension of the SRS.
+    ///
+    /// This does not verify the SRS; callers should do so first, using
+    /// [`verify_structure`].
+    ///
+    /// [`verify_structure`]: #method.verify_structure
+    pub fn powers_of_tau(
+        &self,
+        n: usize,
+    ) -> Result<PowersOfTau<E>, ExtractionError> {
+        if n < 1 || n > self.d {
+            return Err(ExtractionError::Degree { n, d: self.d });
+        }
+        Ok(PowersOfTau {
+            n,
+            g_x: self.g_x[self.d..=self.d + n].to_vec(),
+            h: self.h_x[self.d],
+            h_x: self.h_x[self.d + 1],
+        })
+    }
+}
+
+/// Checks a serialized maximum degree, returning it as a `usize`.
+fn check_degree(n: u64) -> Result<usize, ImportError> {
+    match usize::try_from(n) {
+        Ok(n) if (1..usize::MAX).contains(&n) => Ok(n),
+        _ => Err(ImportError::InvalidLength(n)),
+    }
+}
+
+impl<E: Engine> PowersOfTau<E> {
+    /// The SHA3-256 digest of the reference string, as recorded by [`export`].
+    ///
+    /// This hashes `n` as a little-endian `u64`, followed by the compressed
+    /// points of `g_x`, `h` and `h_x`.
+    ///
+    /// [`export`]: #method.export
+    pub fn digest(&self) -> [u8; 32] {
+        points_digest::<E>(self.n, &[&self.g_x], &[&[self.h, self.h_x]], true)
+    }
+
+    /// The file header describing this reference string.
+    pub fn header(&self, compressed: bool) -> SRSHeader {
+        SRSHeader {
+            magic: POT_MAGIC,
+            version: POT_VERSION,
+            curve: curve_id::<E>(),
+            compressed,
+            d: self.n,
+            digest: points_digest::<E>(
+                self.n,
+                &[&self.g_x],
+                &[&[self.h, self.h_x]],
+                compressed,
+            ),
+        }
+    }
+
+    /// Exports the reference string to a file, with compressed points.
+    ///
+    /// The file is laid out as [`SRSHeader`], with [`POT_MAGIC`] as the magic
+    /// bytes and `n` in place of the dimension. It is followed by the points of
+    /// `g_x`, `h` and `h_x`, in that order.
+    ///
+    /// [`SRSHeader`]: ../usrs/struct.SRSHeader.html
+    /// [`POT_MAGIC`]: constant.POT_MAGIC.html
+    pub fn export<W: Write>(&self, out: W) -> io::Result<()> {
+        self.export_with(out, true)
+    }
+
+    /// Exports the reference string to a file, with uncompressed points.
+    pub fn export_uncompressed<W: Write>(&self, out: W) -> io::Result<()> {
+        self.export_with(out, false)
+    }
+
+    fn export_with<W: Write>(
+        &self,
+        out: W,
+        compressed: bool,
+    ) -> io::Result<()> {
+        write_points_file::<E, _>(
+            out,
+            &self.header(compressed),
+            &[&self.g_x],
+            &[&[self.h, self.h_x]],
+        )
+    }
+
+    /// Imports a reference string written by [`export`] or
+    /// [`export_uncompressed`].
+    ///
+    /// All group elements are decoded and checked to lie in the prime order
+    /// subgroup, and the reference string is checked against the curve and
+    /// digest recorded in its header. The structure itself is *not* verified;
+    /// see [`verify_structure`].
+    ///
+    /// [`export`]: #method.export
+    /// [`export_uncompressed`]: #method.export_uncompressed
+    /// [`verify_structure`]: #method.verify_structure
+    pub fn import<R: Read>(mut inp: R) -> Result<Self, ImportError> {
+        let header = SRSHeader::read_with(
+            &mut inp,
+            POT_MAGIC,
+            POT_VERSION,
+            check_degree,
+        )?;
+        let n = header.d;
+        let (mut gs, hs) =
+            read_points_file::<E, _>(inp, &header, &[n + 1], &[2])?;
+        Ok(PowersOfTau {
+            n,
+            g_x: gs.remove(0),
+            h: hs[0][0],
+            h_x: hs[0][1],
+        })
+    }
+
+    /// Verifies the structure of the reference string.
+    pub fn verify_structure<R: Rng + CryptoRng + ?Sized>(
+        &self,
+        rng: &mut R,
+    ) -> bool {
+        self.try_verify_structure(rng).is_ok()
+    }
+
+    /// Verifies the structure of the reference string, reporting which check
+    /// failed.
+    pub fn try_verify_structure<R: Rng + CryptoRng + ?Sized>(
+        &self,
+        rng: &mut R,
+    ) -> Result<(), VerificationError> {
+        use VerificationError::Structure;
+        let g = E::G1Affine::one();
+        let h = E::G2Affine::one();
+        if self.n == 0 || self.g_x.len() != self.n + 1 {
+            return Err(Structure(StructureCheck::Length));
+        }
+        if self.g_x[0] != g || self.h != h {
+            return Err(Structure(StructureCheck::Generators));
+        }
+        if E::pairing(g, self.h_x) != E::pairing(self.g_x[1], h) {
+            return Err(Structure(StructureCheck::X));
+        }
+        // e(\sum_i r_i g^{x^i}, h^x) = e(\sum_i r_i g^{x^{i + 1}}, h)
+        let rnd = random_scalars::<E::Fr, _>(self.n, rng);
+        let mut neg_hx = self.h_x;
+        neg_hx.negate();
+        let table = [
+            (
+                multiexp(self.g_x[..self.n].iter(), rnd.iter())
+                    .into_affine()
+                    .prepare(),
+                neg_hx.prepare(),
+            ),
+            (
+                multiexp(self.g_x[1..].iter(), rnd.iter())
+                    .into_affine()
+                    .prepare(),
+                h.prepare(),
+            ),
+        ];
+        let table_ref = table.iter().map(|(a, b)| (a, b)).collect::<Vec<_>>();
+        let lp = E::miller_loop(&table_ref[..]);
+        if E::final_exponentiation(&lp).unwrap() != E::Fqk::one() {
+            return Err(Structure(StructureCheck::Powers));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ro::RO;
+    use pairing::bls12_381::{Bls12, G1, G2};
+    use sha3::Sha3_256;
+
+    fn srs() -> USRS<Bls12> {
+        let mut rng = Sha3_256::query(b"kzg test").into_rng();
+        USRS::new(3).permute(&rng.gen())
+    }
+
+    #[test]
+    fn export_round_trip() {
+        let mut rng = Sha3_256::query(b"kzg round trip").into_rng();
+        let pot = srs().powers_of_tau(2).unwrap();
+        assert!(pot.verify_structure(&mut rng));
+        for compressed in [true, false].iter() {
+            let mut buf = Vec::new();
+            if *compressed {
+                pot.export(&mut buf).unwrap();
+            } else {
+                pot.export_uncompressed(&mut buf).unwrap();
+            }
+            let read = PowersOfTau::<Bls12>::import(&buf[..]).unwrap();
+            assert_eq!(read.digest(), pot.digest());
+            buf[SRSHeader::LEN - 1] ^= 1;
+            assert!(matches!(
+                PowersOfTau::<Bls12>::import(&buf[..]),
+                Err(ImportError::DigestMismatch)
+            ));
+        }
+    }
+
+    #[test]
+    fn extraction_checks_degree() {
+        let srs = srs();
+        assert!(srs.powers_of_tau(3).is_ok());
+        for n in [0, 4].iter() {
+            assert_eq!(
+                srs.powers_of_tau(*n).err(),
+                Some(ExtractionError::Degree { n: *n, d: 3 })
+            );
+        }
+    }
+
+    #[test]
+    fn structure_rejects_corrupted_elements() {
+        let mut rng = Sha3_256::query(b"kzg structure").into_rng();
+        let pot = srs().powers_of_tau(3).unwrap();
+        let other = G1::random(&mut rng).into_affine();
+        let cases = [
+            (0, StructureCheck::Generators),
+            (1, StructureCheck::X),
+            (2, StructureCheck::Powers),
+            (3, StructureCheck::Powers),
+        ];
+        for (i, check) in cases.iter() {
+            let mut bad = pot.clone();
+            bad.g_x[*i] = other;
+            assert_eq!(
+                bad.try_verify_structure(&mut rng),
+                Err(VerificationError::Structure(*check))
+            );
+        }
+        let mut bad = pot.clone();
+        bad.h_x = G2::random(&mut rng).into_affine();
+        assert_eq!(
+            bad.try_verify_structure(&mut rng),
+            Err(VerificationError::Structure(StructureCheck::X))
+        );
+        let mut bad = pot;
+        bad.g_x.pop();
+        assert_eq!(
+            bad.try_verify_structure(&mut rng),
+            Err(VerificationError::Structure(StructureCheck::Length))
+        );
+    }
+
+    #[test]
+    fn import_rejects_wrong_magic() {
+        // The formats share a header, but not their magic bytes.
+        let srs = srs();
+        let mut buf = Vec::new();
+        srs.export(&mut buf).unwrap();
+        assert!(matches!(
+            PowersOfTau::<Bls12>::import(&buf[..]),
+            Err(ImportError::BadMagic)
+        ));
+        let mut buf = Vec::new();
+        srs.powers_of_tau(2).unwrap().export(&mut buf).unwrap();
+        assert!(matches!(
+            SRSHeader::read(&buf[..]),
+            Err(ImportError::BadMagic)
+        ));
+        buf[0] ^= 1;
+        assert!(matches!(
+            PowersOfTau::<Bls12>::import(&buf[..]),
+            Err(ImportError::BadMagic)
+        ));
+    }
+}
@@ -1,12 +1,24 @@
 extern crate ff;
 extern crate group;
+extern crate memmap2;
 extern crate pairing;
 extern crate rand;
 extern crate rand_core;
 extern crate rayon;
 extern crate sha3;
 
+pub mod compose;
+#[cfg(feature = "glv")]
+pub mod glv;
+pub mod kzg;
+pub mod linear;
+pub mod multiexp;
+pub mod pc;
 pub mod poe;
 pub mod ro;
+pub mod serialize;
+pub mod stream;
 pub mod usrs;
 pub mod util;
+pub mod view;
+pub mod zcash;
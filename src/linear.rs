@@ -0,0 +1,517 @@
+//! A generic sigma protocol for linear relations over a group, after Maurer
+//! [1].
+//!
+//! A statement is a matrix `M` of bases together with its image `X = M w` for a
+//! secret witness vector `w`; that is, `X_i = \prod_j M_{ij}^{w_j}`. Proofs of
+//! knowledge of exponents, of equal discrete logarithms, and of Pedersen
+//! commitment openings are all of this form, and are declared with the
+//! constructors of [`LinearStatement`] instead of as separate protocols.
+//!
+//! The statement includes the matrix, so it is bound by both transforms'
+//! challenges. Verifiers must still check that the matrix is the one they
+//! expect.
+//!
+//! [1] Ueli Maurer. Unifying zero-knowledge proofs of knowledge.
+//! AFRICACRYPT 2009.
+//!
+//! [`LinearStatement`]: struct.LinearStatement.html
+
+use crate::poe::{
+    CurvePoint, FieldElement, Relation, SigmaProtocol,
+    SimulatableSigmaProtocol, UniqueResponses,
+};
+use crate::serialize::Serializable;
+use crate::usrs::ImportError;
+use crate::util::batch_into_affine;
+use ff::{Field, PrimeField};
+use group::{CurveAffine, CurveProjective};
+use rand::{CryptoRng, Rng};
+use std::io::{self, Read, Write};
+use std::marker::PhantomData;
+
+/// The sigma protocol proving knowledge of `w` with `X = M w`.
+pub struct LinearSigmaProtocol<C: CurveAffine>(PhantomData<C>);
+
+/// A linear relation `X = M w` over a group.
+///
+/// Each row of the matrix is stored sparsely, as pairs of a witness index and
+/// the base it is raised to.
+#[derive(Clone, PartialEq, Eq)]
+pub struct LinearStatement<C: CurveAffine> {
+    witnesses: usize,
+    rows: Vec<Vec<(usize, C)>>,
+    image: Vec<C>,
+    bytes: Vec<u8>,
+}
+
+impl<C: CurveAffine> LinearStatement<C> {
+    /// Creates the statement `image = rows w`, for a witness of `witnesses`
+    /// scalars.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rows` and `image` differ in length, or if a row refers to a
+    /// witness index of at least `witnesses`.
+    pub fn new(
+        witnesses: usize,
+        rows: Vec<Vec<(usize, C)>>,
+        image: Vec<C>,
+    ) -> Self {
+        assert_eq!(rows.len(), image.len(), "one image element per row");
+        assert!(
+            rows.iter().flatten().all(|(j, _)| *j < witnesses),
+            "witness index out of range"
+        );
+        let mut bytes = Vec::new();
+        bytes.extend((witnesses as u64).to_le_bytes().iter());
+        bytes.extend((rows.len() as u64).to_le_bytes().iter());
+        for row in rows.iter() {
+            bytes.extend((row.len() as u64).to_le_bytes().iter());
+            for (j, g) in row.iter() {
+                bytes.extend((*j as u64).to_le_bytes().iter());
+                bytes.extend(g.into_uncompressed().as_ref().iter());
+            }
+        }
+        for x in image.iter() {
+            bytes.extend(x.into_uncompressed().as_ref().iter());
+        }
+        LinearStatement {
+            witnesses,
+            rows,
+            image,
+            bytes,
+        }
+    }
+
+    /// Knowledge of `a` with `x = g^a`, as in [`SchnorrSigmaProtocol`].
+    ///
+    /// [`SchnorrSigmaProtocol`]: ../poe/struct.SchnorrSigmaProtocol.html
+    pub fn schnorr(x: C) -> Self {
+        Self::new(1, vec![vec![(0, C::one())]], vec![x])
+    }
+
+    /// Knowledge of `a` with `ga = g^a` and `ua = u^a`, as in
+    /// [`DLEQSigmaProtocol`].
+    ///
+    /// [`DLEQSigmaProtocol`]: ../poe/struct.DLEQSigmaProtocol.html
+    pub fn dleq(g: C, u: C, ga: C, ua: C) -> Self {
+        Self::new(1, vec![vec![(0, g)], vec![(0, u)]], vec![ga, ua])
+    }
+
+    /// Knowledge of `a` and `b` with `x = g^a` and `y = g^b`, as in
+    /// [`DualProofOfExponentSigmaProtocol`].
+    ///
+    /// [`DualProofOfExponentSigmaProtocol`]: ../poe/struct.DualProofOfExponentSigmaProtocol.html
+    pub fn dual(x: C, y: C) -> Self {
+        Self::new(
+            2,
+            vec![vec![(0, C::one())], vec![(1, C::one())]],
+            vec![x, y],
+        )
+    }
+
+    /// Knowledge of an opening `(m, r)` of the Pedersen commitment
+    /// `c = g^m h^r`.
+    pub fn pedersen(g: C, h: C, c: C) -> Self {
+        Self::new(2, vec![vec![(0, g), (1, h)]], vec![c])
+    }
+
+    /// The number of scalars in a witness.
+    pub fn witnesses(&self) -> usize {
+        self.witnesses
+    }
+
+    /// The rows of the matrix `M`.
+    pub fn rows(&self) -> &[Vec<(usize, C)>] {
+        &self.rows
+    }
+
+    /// The image `X`.
+    pub fn image(&self) -> &[C] {
+        &self.image
+    }
+
+    /// Computes `M w`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `w` is too short for the matrix.
+    pub fn apply(&self, w: &[C::Scalar]) -> Vec<C> {
+        batch_into_affine(
+            self.rows
+                .iter()
+                .map(|row| {
+                    row.iter().fold(C::Projective::zero(), |mut acc, (j, g)| {
+                        acc.add_assign(&g.mul(w[*j]));
+                        acc
+                    })
+                })
+                .collect(),
+        )
+    }
+}
+
+impl<C: CurveAffine> AsRef<[u8]> for LinearStatement<C> {
+    fn as_ref(&self) -> &[u8] {
+        self.bytes.as_ref()
+    }
+}
+
+#[derive(Clone, PartialEq, Eq)]
+/// A vector of curve elements
+pub struct CurveVector<C: CurveAffine>(pub Vec<C>, Vec<u8>);
+
+impl<C: CurveAffine> CurveVector<C> {
+    pub fn new(v: Vec<C>) -> Self {
+        let mut vec = Vec::new();
+        for p in v.iter() {
+            vec.extend(p.into_uncompressed().as_ref().iter().cloned());
+        }
+        CurveVector(v, vec)
+    }
+}
+
+#[derive(Clone)]
+/// A vector of field elements
+pub struct FieldVector<F: PrimeField>(pub Vec<F>, Vec<u8>);
+
+impl<F: PrimeField> FieldVector<F> {
+    pub fn new(v: Vec<F>) -> Self {
+        let mut vec = Vec::new();
+        for i in v.iter() {
+            for word in i.into_repr().as_ref() {
+                vec.extend(word.to_le_bytes().iter())
+            }
+        }
+        FieldVector(v, vec)
+    }
+}
+
+impl<C: CurveAffine> AsRef<[u8]> for CurveVector<C> {
+    fn as_ref(&self) -> &[u8] {
+        self.1.as_ref()
+    }
+}
+
+impl<F: PrimeField> AsRef<[u8]> for FieldVector<F> {
+    fn as_ref(&self) -> &[u8] {
+        self.1.as_ref()
+    }
+}
+
+/// Encoded as a `Vec` of [`CurvePoint`]s.
+///
+/// [`CurvePoint`]: ../poe/struct.CurvePoint.html
+impl<C: CurveAffine> Serializable for CurveVector<C> {
+    fn write<W: Write>(&self, out: W) -> io::Result<()> {
+        let v = self
+            .0
+            .iter()
+            .map(|p| CurvePoint::new(*p))
+            .collect::<Vec<_>>();
+        v.write(out)
+    }
+
+    fn read<R: Read>(inp: R) -> Result<Self, ImportError> {
+        let v = Vec::<CurvePoint<C>>::read(inp)?;
+        Ok(CurveVector::new(v.into_iter().map(|p| p.0).collect()))
+    }
+}
+
+/// Encoded as a `Vec` of [`FieldElement`]s.
+///
+/// [`FieldElement`]: ../poe/struct.FieldElement.html
+impl<F: PrimeField> Serializable for FieldVector<F> {
+    fn write<W: Write>(&self, out: W) -> io::Result<()> {
+        let v = self
+            .0
+            .iter()
+            .map(|f| FieldElement::new(*f))
+            .collect::<Vec<_>>();
+        v.write(out)
+    }
+
+    fn read<R: Read>(inp: R) -> Result<Self, ImportError> {
+        let v = Vec::<FieldElement<F>>::read(inp)?;
+        Ok(FieldVector::new(v.into_iter().map(|f| f.0).collect()))
+    }
+}
+
+impl<C: CurveAffine> Relation for LinearSigmaProtocol<C> {
+    type X = LinearStatement<C>;
+    type W = FieldVector<C::Scalar>;
+
+    fn check(x: &Self::X, w: &Self::W) -> bool {
+        w.0.len() == x.witnesses && x.apply(&w.0) == x.image
+    }
+}
+
+impl<C: CurveAffine> SigmaProtocol for LinearSigmaProtocol<C> {
+    type Z = FieldVector<C::Scalar>;
+    type T = CurveVector<C>;
+    type C = FieldElement<C::Scalar>;
+    type R = FieldVector<C::Scalar>;
+
+    fn prove_step_1<R: Rng + CryptoRng + ?Sized>(
+        x: &Self::X,
+        _: &Self::W,
+        rng: &mut R,
+    ) -> (Self::Z, Self::T) {
+        let z = (0..x.witnesses)
+            .map(|_| C::Scalar::random(rng))
+            .collect::<Vec<_>>();
+        let t = CurveVector::new(x.apply(&z));
+        (FieldVector::new(z), t)
+    }
+
+    fn prove_step_2(
+        _: &Self::X,
+        w: &Self::W,
+        z: &Self::Z,
+        c: &Self::C,
+    ) -> Self::R {
+        let c = c.0;
+        FieldVector::new(
+            w.0.iter()
+                .zip(z.0.iter())
+                .map(|(w, z)| {
+                    let mut cw = c;
+                    cw.mul_assign(w);
+                    let mut r = *z;
+                    r.sub_assign(&cw);
+                    r
+                })
+                .collect(),
+        )
+    }
+
+    fn finish_verify(
+        x: &Self::X,
+        t: &Self::T,
+        c: &Self::C,
+        r: &Self::R,
+    ) -> bool {
+        let c = c.0;
+        if t.0.len() != x.rows.len() || r.0.len() != x.witnesses {
+            return false;
+        }
+        // M r + c X = M (z - c w) + c M w = M z = T
+        let mr = x.apply(&r.0);
+        mr.iter()
+            .zip(x.image.iter())
+            .zip(t.0.iter())
+            .all(|((mr, x), t)| {
+                let mut t_prime = x.mul(c);
+                t_prime.add_assign_mixed(mr);
+                t_prime.into_affine() == *t
+            })
+    }
+}
+
+/// Two responses for the same commitment and challenge differ by a vector `d`
+/// with `M d = 0`. Unless the statement's bases have a known relation, finding
+/// one is as hard as computing discrete logarithms.
+impl<C: CurveAffine> UniqueResponses for LinearSigmaProtocol<C> {}
+
+impl<C: CurveAffine> SimulatableSigmaProtocol for LinearSigmaProtocol<C> {
+    fn simulate<R: Rng + CryptoRng + ?Sized>(
+        x: &Self::X,
+        c: &Self::C,
+        rng: &mut R,
+    ) -> (Self::T, Self::R) {
+        let r = (0..x.witnesses)
+            .map(|_| C::Scalar::random(rng))
+            .collect::<Vec<_>>();
+        // T = M r + c X
+        let t = x
+            .apply(&r)
+            .iter()
+            .zip(x.image.iter())
+            .map(|(mr, x)| {
+                let mut t = x.mul(c.0);
+                t.add_assign_mixed(mr);
+                t
+            })
+            .collect();
+        (CurveVector::new(batch_into_affine(t)), FieldVector::new(r))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poe::{
+        CurvePair, DLEQSigmaProtocol, DLEQStatement,
+        DualProofOfExponentSigmaProtocol, FieldPair, SchnorrSigmaProtocol,
+    };
+    use crate::ro::RO;
+    use pairing::bls12_381::{Fr, G1Affine, G1};
+    use sha3::Sha3_256;
+
+    type Linear = LinearSigmaProtocol<G1Affine>;
+
+    fn rng() -> impl Rng + CryptoRng {
+        Sha3_256::query(b"linear test").into_rng()
+    }
+
+    fn random_point<R: Rng>(rng: &mut R) -> G1Affine {
+        G1::random(rng).into_affine()
+    }
+
+    /// Checks that `T` and the linear protocol for `lx` accept the same
+    /// witnesses, and each other's honest transcripts for challenge `c`, which
+    /// `T` takes as `tc`.
+    #[allow(clippy::too_many_arguments)]
+    fn check_agreement<T: SigmaProtocol>(
+        x: &T::X,
+        w: &T::W,
+        lx: &LinearStatement<G1Affine>,
+        lw: &FieldVector<Fr>,
+        c: Fr,
+        tc: &T::C,
+        to_linear: impl Fn(&T::T, &T::R) -> (CurveVector<G1Affine>, FieldVector<Fr>),
+        from_linear: impl Fn(
+            &CurveVector<G1Affine>,
+            &FieldVector<Fr>,
+        ) -> (T::T, T::R),
+    ) {
+        let mut rng = Sha3_256::query(b"linear agreement").into_rng();
+        assert!(T::check(x, w) && Linear::check(lx, lw));
+        let wrong = FieldVector::new(
+            lw.0.iter().map(|_| Fr::random(&mut rng)).collect(),
+        );
+        assert!(!Linear::check(lx, &wrong));
+
+        let lc = FieldElement::new(c);
+        let (z, t) = T::prove_step_1(x, w, &mut rng);
+        let r = T::prove_step_2(x, w, &z, tc);
+        assert!(T::finish_verify(x, &t, tc, &r));
+        let (lt, lr) = to_linear(&t, &r);
+        assert!(Linear::finish_verify(lx, &lt, &lc, &lr));
+
+        let (z, lt) = Linear::prove_step_1(lx, lw, &mut rng);
+        let lr = Linear::prove_step_2(lx, lw, &z, &lc);
+        assert!(Linear::finish_verify(lx, &lt, &lc, &lr));
+        let (t, r) = from_linear(&lt, &lr);
+        assert!(T::finish_verify(x, &t, tc, &r));
+        assert!(!Linear::finish_verify(lx, &lt, &lc, &wrong));
+    }
+
+    #[test]
+    fn schnorr_agrees() {
+        let mut rng = rng();
+        let a = Fr::random(&mut rng);
+        let x = G1Affine::one().mul(a).into_affine();
+        let c = Fr::random(&mut rng);
+        check_agreement::<SchnorrSigmaProtocol<G1Affine>>(
+            &CurvePoint::new(x),
+            &FieldElement::new(a),
+            &LinearStatement::schnorr(x),
+            &FieldVector::new(vec![a]),
+            c,
+            &FieldElement::new(c),
+            |t, r| (CurveVector::new(vec![t.0]), FieldVector::new(vec![r.0])),
+            |t, r| (CurvePoint::new(t.0[0]), FieldElement::new(r.0[0])),
+        );
+    }
+
+    #[test]
+    fn dleq_agrees() {
+        let mut rng = rng();
+        let (g, u) = (random_point(&mut rng), random_point(&mut rng));
+        let a = Fr::random(&mut rng);
+        let (ga, ua) = (g.mul(a).into_affine(), u.mul(a).into_affine());
+        let c = Fr::random(&mut rng);
+        check_agreement::<DLEQSigmaProtocol<G1Affine>>(
+            &DLEQStatement::new(g, u, ga, ua),
+            &FieldElement::new(a),
+            &LinearStatement::dleq(g, u, ga, ua),
+            &FieldVector::new(vec![a]),
+            c,
+            &FieldElement::new(c),
+            |t, r| {
+                (
+                    CurveVector::new(vec![t.0, t.1]),
+                    FieldVector::new(vec![r.0]),
+                )
+            },
+            |t, r| (CurvePair::new(t.0[0], t.0[1]), FieldElement::new(r.0[0])),
+        );
+    }
+
+    #[test]
+    fn dual_agrees() {
+        let mut rng = rng();
+        let (a, b) = (Fr::random(&mut rng), Fr::random(&mut rng));
+        let x = G1Affine::one().mul(a).into_affine();
+        let y = G1Affine::one().mul(b).into_affine();
+        // The dual proof has a challenge per exponent; the linear protocol uses
+        // one for both.
+        let c = Fr::random(&mut rng);
+        check_agreement::<DualProofOfExponentSigmaProtocol<G1Affine>>(
+            &CurvePair::new(x, y),
+            &FieldPair::new(a, b),
+            &LinearStatement::dual(x, y),
+            &FieldVector::new(vec![a, b]),
+            c,
+            &FieldPair::new(c, c),
+            |t, r| {
+                (
+                    CurveVector::new(vec![t.0, t.1]),
+                    FieldVector::new(vec![r.0, r.1]),
+                )
+            },
+            |t, r| {
+                (
+                    CurvePair::new(t.0[0], t.0[1]),
+                    FieldPair::new(r.0[0], r.0[1]),
+                )
+            },
+        );
+    }
+
+    #[test]
+    fn pedersen_opens_commitment() {
+        let mut rng = rng();
+        let (g, h) = (random_point(&mut rng), random_point(&mut rng));
+        let (m, r) = (Fr::random(&mut rng), Fr::random(&mut rng));
+        let mut c = g.mul(m);
+        c.add_assign(&h.mul(r));
+        let x = LinearStatement::pedersen(g, h, c.into_affine());
+        assert!(Linear::check(&x, &FieldVector::new(vec![m, r])));
+        assert!(!Linear::check(&x, &FieldVector::new(vec![r, m])));
+        let c = FieldElement::new(Fr::random(&mut rng));
+        let (z, t) =
+            Linear::prove_step_1(&x, &FieldVector::new(vec![m, r]), &mut rng);
+        let resp =
+            Linear::prove_step_2(&x, &FieldVector::new(vec![m, r]), &z, &c);
+        assert!(Linear::finish_verify(&x, &t, &c, &resp));
+        let (t, resp) = Linear::simulate(&x, &c, &mut rng);
+        assert!(Linear::finish_verify(&x, &t, &c, &resp));
+    }
+
+    #[test]
+    fn vectors_encode_as_vecs() {
+        let mut rng = rng();
+        let points = vec![random_point(&mut rng), random_point(&mut rng)];
+        let scalars = vec![Fr::random(&mut rng); 3];
+        let mut bytes = Vec::new();
+        CurveVector::new(points.clone()).write(&mut bytes).unwrap();
+        let mut expected = Vec::new();
+        points
+            .iter()
+            .map(|p| CurvePoint::new(*p))
+            .collect::<Vec<_>>()
+            .write(&mut expected)
+            .unwrap();
+        assert_eq!(bytes, expected);
+        assert!(
+            CurveVector::<G1Affine>::read(&bytes[..]).unwrap()
+                == CurveVector::new(points)
+        );
+        let mut bytes = Vec::new();
+        FieldVector::new(scalars.clone()).write(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), 8 + 3 * 32);
+        assert_eq!(FieldVector::<Fr>::read(&bytes[..]).unwrap().0, scalars);
+    }
+}
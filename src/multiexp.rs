@@ -0,0 +1,921 @@
+//! Multi-exponentiation: computing `\sum_i s_i g_i` for many points at once.
+//!
+//! [`multiexp`] is the general entry point, taking affine bases and scalars as
+//! separate iterators. [`multiexp_projective`] and [`multiexp_pairs`] accept
+//! the other common shapes of input. When many multi-exponentiations share the
+//! same bases, such as commitments over the vectors of a [`USRS`],
+//! [`PrecomputedBases`] trades memory for avoiding all doublings. For groups
+//! implementing [`Coordinates`], [`multiexp_batch_affine`] accumulates buckets
+//! in affine coordinates, at nearly half the cost per point.
+//!
+//! [`multiexp`]: fn.multiexp.html
+//! [`multiexp_batch_affine`]: fn.multiexp_batch_affine.html
+//! [`Coordinates`]: trait.Coordinates.html
+//! [`multiexp_projective`]: fn.multiexp_projective.html
+//! [`multiexp_pairs`]: fn.multiexp_pairs.html
+//! [`PrecomputedBases`]: struct.PrecomputedBases.html
+//! [`USRS`]: ../usrs/struct.USRS.html
+
+use crate::util::{batch_into_affine, Parallelism};
+use ff::{Field, PrimeField, PrimeFieldRepr, ScalarEngine};
+use group::{CurveAffine, CurveProjective, EncodedPoint};
+use pairing::bls12_381::{Fq, Fq2, FqRepr, G1Affine, G2Affine};
+use rayon::prelude::*;
+
+/// The cost, in field multiplications, of adding a point into a bucket with
+/// batch-affine addition: three multiplications for the shared inversion, and
+/// three for the addition itself.
+const AFFINE_ADD_COST: usize = 6;
+
+/// The cost, in field multiplications, of adding a point into a bucket with
+/// mixed addition.
+const MIXED_ADD_COST: usize = 11;
+
+/// The cost, in field multiplications, of adding two projective points, as in
+/// summing buckets.
+const PROJECTIVE_ADD_COST: usize = 16;
+
+/// The number of points queued for a round of batch-affine additions.
+const BATCH_SIZE: usize = 1 << 10;
+
+/// The fewest additions in a round for which a shared inversion beats mixed
+/// addition.
+const MIN_BATCH: usize = 1 << 6;
+
+/// The size of an encoded base field element of BLS12-381.
+const FQ_SIZE: usize = 48;
+
+/// The affine coordinates `(x, y)` of a point of `G`.
+type Xy<G> = (<G as CurveAffine>::Base, <G as CurveAffine>::Base);
+
+/// Converts between points and their affine coordinates, which `group` does not
+/// expose.
+///
+/// This is what [`multiexp_batch_affine`] needs of a group, which must also
+/// have the form `y^2 = x^3 + b`. It is implemented for both groups of
+/// BLS12-381, whose conversions go through the uncompressed encoding and cost
+/// little next to the additions they enable.
+///
+/// [`multiexp_batch_affine`]: fn.multiexp_batch_affine.html
+pub trait Coordinates: CurveAffine {
+    /// The affine coordinates `(x, y)` of the point, or `None` for the point at
+    /// infinity.
+    fn to_xy(&self) -> Option<Xy<Self>>;
+
+    /// The point with affine coordinates `(x, y)`, which must lie in the prime
+    /// order subgroup.
+    fn from_xy(x: Self::Base, y: Self::Base) -> Self;
+}
+
+/// Reads the base field element encoded in `bytes`.
+fn read_fq(bytes: &[u8]) -> Fq {
+    let mut repr = FqRepr::default();
+    repr.read_be(bytes)
+        .expect("the encoding holds a full coordinate");
+    Fq::from_repr(repr).expect("the coordinate is reduced")
+}
+
+/// Writes the base field element `x` into `bytes`.
+fn write_fq(x: &Fq, bytes: &mut [u8]) {
+    x.into_repr()
+        .write_be(bytes)
+        .expect("the encoding holds a full coordinate");
+}
+
+impl Coordinates for G1Affine {
+    fn to_xy(&self) -> Option<(Fq, Fq)> {
+        if self.is_zero() {
+            return None;
+        }
+        let enc = self.into_uncompressed();
+        let enc = enc.as_ref();
+        Some((read_fq(&enc[..FQ_SIZE]), read_fq(&enc[FQ_SIZE..])))
+    }
+
+    fn from_xy(x: Fq, y: Fq) -> Self {
+        let mut enc = <Self as CurveAffine>::Uncompressed::empty();
+        write_fq(&x, &mut enc.as_mut()[..FQ_SIZE]);
+        write_fq(&y, &mut enc.as_mut()[FQ_SIZE..]);
+        enc.into_affine_unchecked()
+            .expect("a finite point is encoded without flags")
+    }
+}
+
+impl Coordinates for G2Affine {
+    fn to_xy(&self) -> Option<(Fq2, Fq2)> {
+        if self.is_zero() {
+            return None;
+        }
+        // Each coordinate is encoded as c1 then c0.
+        let enc = self.into_uncompressed();
+        let fq = |i: usize| read_fq(&enc.as_ref()[i * FQ_SIZE..][..FQ_SIZE]);
+        Some((
+            Fq2 {
+                c0: fq(1),
+                c1: fq(0),
+            },
+            Fq2 {
+                c0: fq(3),
+                c1: fq(2),
+            },
+        ))
+    }
+
+    fn from_xy(x: Fq2, y: Fq2) -> Self {
+        let mut enc = <Self as CurveAffine>::Uncompressed::empty();
+        for (i, c) in [x.c1, x.c0, y.c1, y.c0].iter().enumerate() {
+            write_fq(c, &mut enc.as_mut()[i * FQ_SIZE..][..FQ_SIZE]);
+        }
+        enc.into_affine_unchecked()
+            .expect("a finite point is encoded without flags")
+    }
+}
+
+/// The [`Coordinates`] conversions for `G`, passed by [`multiexp_batch_affine`]
+/// to the bucket code it shares with [`multiexp`].
+///
+/// [`Coordinates`]: trait.Coordinates.html
+/// [`multiexp_batch_affine`]: fn.multiexp_batch_affine.html
+/// [`multiexp`]: fn.multiexp.html
+struct CoordinateMaps<G: CurveAffine> {
+    to_xy: fn(&G) -> Option<Xy<G>>,
+    from_xy: fn(G::Base, G::Base) -> G,
+}
+
+impl<G: CurveAffine> Clone for CoordinateMaps<G> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<G: CurveAffine> Copy for CoordinateMaps<G> {}
+
+impl<G: Coordinates> CoordinateMaps<G> {
+    fn new() -> Self {
+        CoordinateMaps {
+            to_xy: G::to_xy,
+            from_xy: G::from_xy,
+        }
+    }
+}
+
+/// Replaces each element of `v` by its inverse, with a single inversion.
+///
+/// This is Montgomery's trick: invert the product of all elements, then peel
+/// off one element at a time.
+///
+/// # Panics
+///
+/// Panics if an element is zero.
+fn batch_invert<F: Field>(v: &mut [F]) {
+    let mut prefixes = Vec::with_capacity(v.len());
+    let mut acc = F::one();
+    for x in v.iter() {
+        prefixes.push(acc);
+        acc.mul_assign(x);
+    }
+    let mut inv = acc.inverse().expect("no element is zero");
+    for (x, prefix) in v.iter_mut().zip(prefixes).rev() {
+        let mut x_inv = inv;
+        x_inv.mul_assign(&prefix);
+        inv.mul_assign(x);
+        *x = x_inv;
+    }
+}
+
+/// Buckets accumulated in affine coordinates, so that the additions of a round
+/// can share a single inversion.
+///
+/// A round adds at most one point into each bucket, deferring the rest to the
+/// next round. Once a round would have too few additions to amortise its
+/// inversion, as when most points fall into a few buckets, its points are added
+/// with mixed addition instead. The curve must have the form `y^2 = x^3 + b`,
+/// as both groups of BLS12-381 do.
+struct AffineBuckets<G: CurveAffine> {
+    maps: CoordinateMaps<G>,
+    affine: Vec<Option<Xy<G>>>,
+    /// Buckets holding the points added with mixed addition.
+    projective: Vec<G::Projective>,
+    /// Whether each bucket already has an addition in the current round.
+    busy: Vec<bool>,
+    /// The points queued for the next round, with their buckets.
+    pending: Vec<(usize, Xy<G>)>,
+}
+
+impl<G: CurveAffine> AffineBuckets<G> {
+    fn new(maps: CoordinateMaps<G>, n: usize) -> Self {
+        AffineBuckets {
+            maps,
+            affine: vec![None; n],
+            projective: vec![G::Projective::zero(); n],
+            busy: vec![false; n],
+            pending: Vec::with_capacity(BATCH_SIZE),
+        }
+    }
+
+    /// Adds `p`, negated if `digit` is negative, to the bucket for `digit`.
+    fn add(&mut self, digit: isize, p: &Xy<G>) {
+        let (x, mut y) = *p;
+        if digit == 0 {
+            return;
+        } else if digit < 0 {
+            y.negate();
+        }
+        self.pending.push((digit.unsigned_abs() - 1, (x, y)));
+        if self.pending.len() == BATCH_SIZE {
+            self.round();
+        }
+    }
+
+    /// Performs one round of additions, leaving the deferred points pending.
+    fn round(&mut self) {
+        let mut adds = Vec::new();
+        let mut denominators = Vec::new();
+        let mut deferred = Vec::new();
+        for (b, (x, y)) in self.pending.drain(..) {
+            if self.busy[b] {
+                deferred.push((b, (x, y)));
+                continue;
+            }
+            let (x1, y1) = match self.affine[b] {
+                None => {
+                    self.affine[b] = Some((x, y));
+                    continue;
+                }
+                Some(q) => q,
+            };
+            // No point of the prime order subgroup has y = 0, so doubling never
+            // divides by zero.
+            let den = if x1 != x {
+                let mut den = x;
+                den.sub_assign(&x1);
+                den
+            } else if y1 == y {
+                let mut den = y;
+                den.double();
+                den
+            } else {
+                // The points are each other's negation.
+                self.affine[b] = None;
+                continue;
+            };
+            self.busy[b] = true;
+            adds.push((b, (x, y)));
+            denominators.push(den);
+        }
+        if adds.len() < MIN_BATCH {
+            for (b, p) in adds.into_iter().chain(deferred) {
+                self.busy[b] = false;
+                let p = (self.maps.from_xy)(p.0, p.1);
+                self.projective[b].add_assign_mixed(&p);
+            }
+            return;
+        }
+        batch_invert(&mut denominators);
+        for ((b, (x2, y2)), inv) in adds.into_iter().zip(denominators) {
+            self.busy[b] = false;
+            let (x1, y1) = self.affine[b].expect("the bucket was not empty");
+            // The slope, (y2 - y1) / (x2 - x1), or 3 x1^2 / 2 y1 when doubling.
+            let mut lambda = if x1 == x2 {
+                let mut l = x1;
+                l.square();
+                let mut three = l;
+                three.double();
+                three.add_assign(&l);
+                three
+            } else {
+                let mut l = y2;
+                l.sub_assign(&y1);
+                l
+            };
+            lambda.mul_assign(&inv);
+            let mut x3 = lambda;
+            x3.square();
+            x3.sub_assign(&x1);
+            x3.sub_assign(&x2);
+            let mut y3 = x1;
+            y3.sub_assign(&x3);
+            y3.mul_assign(&lambda);
+            y3.sub_assign(&y1);
+            self.affine[b] = Some((x3, y3));
+        }
+        self.pending = deferred;
+    }
+
+    /// Completes the pending additions and returns the buckets.
+    fn finish(mut self) -> Vec<G::Projective> {
+        while !self.pending.is_empty() {
+            self.round();
+        }
+        let from_xy = self.maps.from_xy;
+        for (bucket, affine) in self.projective.iter_mut().zip(self.affine) {
+            if let Some((x, y)) = affine {
+                bucket.add_assign_mixed(&from_xy(x, y));
+            }
+        }
+        self.projective
+    }
+}
+
+/// Chooses the window size of [`multiexp`], and the number of parts to split
+/// its input into, minimising the work of the busiest of `threads` threads.
+///
+/// Each window of each part is one job, adding its points into buckets at
+/// `add_cost` multiplications each, and then summing `2^{c-1}` buckets with two
+/// projective additions each.
+///
+/// [`multiexp`]: fn.multiexp.html
+fn multiexp_params(
+    n: usize,
+    bits: usize,
+    threads: usize,
+    add_cost: usize,
+) -> (usize, usize) {
+    let threads = threads.max(1);
+    let mut best = (1, 1, usize::MAX);
+    for c in 1..=bits.min(24) {
+        let windows = signed_windows(bits, c);
+        for parts in 1..=threads {
+            let rounds = (windows * parts).div_ceil(threads);
+            let cost = rounds
+                * (n.div_ceil(parts) * add_cost
+                    + (1 << c) * PROJECTIVE_ADD_COST);
+            if cost < best.2 {
+                best = (c, parts, cost);
+            }
+        }
+    }
+    (best.0, best.1)
+}
+
+/// The number of `c` bit signed digits needed for a `bits` bit scalar.
+///
+/// The top digit may carry into one bit beyond the scalar.
+fn signed_windows(bits: usize, c: usize) -> usize {
+    (bits + 1).div_ceil(c)
+}
+
+/// The `c` bits of `repr` starting at bit `offset`.
+fn window_bits<R: AsRef<[u64]>>(repr: &R, offset: usize, c: usize) -> usize {
+    let limbs = repr.as_ref();
+    let (limb, shift) = (offset / 64, offset % 64);
+    if limb >= limbs.len() {
+        return 0;
+    }
+    let mut bits = limbs[limb] >> shift;
+    if shift + c > 64 && limb + 1 < limbs.len() {
+        bits |= limbs[limb + 1] << (64 - shift);
+    }
+    (bits & ((1u64 << c) - 1)) as usize
+}
+
+/// The `window`th digit of `repr` in signed radix `2^c`, in
+/// `[-2^{c-1}, 2^{c-1}]`.
+///
+/// This is Booth recoding: a window whose top bit is set is read as negative,
+/// and the window above adds that bit back in. Each digit depends only on
+/// `c + 1` bits of the scalar, so the windows can still be processed
+/// independently.
+fn signed_digit<R: AsRef<[u64]>>(repr: &R, window: usize, c: usize) -> isize {
+    let offset = window * c;
+    let bits = window_bits(repr, offset, c) as isize;
+    let borrow = if offset == 0 {
+        0
+    } else {
+        window_bits(repr, offset - 1, 1) as isize
+    };
+    bits + borrow - ((bits >> (c - 1)) << c)
+}
+
+/// Adds `g` to the bucket for `digit`, negating it if `digit` is negative.
+fn add_to_bucket<G: CurveAffine>(
+    buckets: &mut [G::Projective],
+    digit: isize,
+    g: &G,
+) {
+    if digit > 0 {
+        buckets[digit as usize - 1].add_assign_mixed(g);
+    } else if digit < 0 {
+        let mut g = *g;
+        g.negate();
+        buckets[(-digit) as usize - 1].add_assign_mixed(&g);
+    }
+}
+
+/// Computes `\sum_i (i + 1) buckets[i]`, with two additions per bucket.
+fn sum_buckets<G: CurveProjective>(buckets: &[G]) -> G {
+    let mut acc = G::zero();
+    let mut running_sum = G::zero();
+    for exp in buckets.iter().rev() {
+        running_sum.add_assign(exp);
+        acc.add_assign(&running_sum);
+    }
+    acc
+}
+
+/// Computes `\sum_i s_i g_i` using Pippenger's algorithm.
+///
+/// Scalars are split into signed digits, so each window needs only `2^{c-1}`
+/// buckets; a point with a negative digit is negated before being added to its
+/// bucket. The window size is chosen from both the number of points and the
+/// number of threads available; when there are more threads than windows, the
+/// points are also split between threads. Points are added into projective
+/// buckets with mixed addition.
+///
+/// # Panics
+///
+/// Panics if `g` and `s` differ in length.
+pub fn multiexp<
+    'a,
+    G: CurveAffine,
+    IB: IntoIterator<Item = &'a G>,
+    IS: IntoIterator<Item = &'a G::Scalar>,
+>(
+    g: IB,
+    s: IS,
+) -> G::Projective
+where
+    IB::IntoIter: ExactSizeIterator + Clone + Send,
+    IS::IntoIter: ExactSizeIterator + Clone + Send,
+{
+    multiexp_with(g, s, None)
+}
+
+/// Computes `\sum_i s_i g_i` as [`multiexp`] does, but accumulating buckets in
+/// affine coordinates.
+///
+/// The additions into different buckets share one inversion by Montgomery's
+/// trick, which is nearly half the cost of mixed addition into projective
+/// buckets. Jobs with too few points to fill a round of additions still use
+/// projective buckets.
+///
+/// [`multiexp`]: fn.multiexp.html
+///
+/// # Panics
+///
+/// Panics if `g` and `s` differ in length.
+pub fn multiexp_batch_affine<
+    'a,
+    G: Coordinates,
+    IB: IntoIterator<Item = &'a G>,
+    IS: IntoIterator<Item = &'a G::Scalar>,
+>(
+    g: IB,
+    s: IS,
+) -> G::Projective
+where
+    IB::IntoIter: ExactSizeIterator + Clone + Send,
+    IS::IntoIter: ExactSizeIterator + Clone + Send,
+{
+    multiexp_with(g, s, Some(CoordinateMaps::new()))
+}
+
+/// Computes `\sum_i s_i g_i`, with affine buckets when given the coordinate
+/// conversions `maps`.
+fn multiexp_with<
+    'a,
+    G: CurveAffine,
+    IB: IntoIterator<Item = &'a G>,
+    IS: IntoIterator<Item = &'a G::Scalar>,
+>(
+    g: IB,
+    s: IS,
+    maps: Option<CoordinateMaps<G>>,
+) -> G::Projective
+where
+    IB::IntoIter: ExactSizeIterator + Clone + Send,
+    IS::IntoIter: ExactSizeIterator + Clone + Send,
+{
+    let g = g.into_iter();
+    let s = s.into_iter();
+    assert_eq!(g.len(), s.len());
+    let n = s.len();
+    if n == 0 {
+        return G::Projective::zero();
+    }
+    let bits = <G::Engine as ScalarEngine>::Fr::NUM_BITS as usize;
+    let threads = rayon::current_num_threads();
+    let add_cost = if maps.is_some() {
+        AFFINE_ADD_COST
+    } else {
+        MIXED_ADD_COST
+    };
+    let (c, parts) = multiexp_params(n, bits, threads, add_cost);
+    let windows = signed_windows(bits, c);
+    let part_len = n.div_ceil(parts).max(1);
+
+    // Convert all of the scalars into representations
+    let s = s.map(|s| s.into_repr()).collect::<Vec<_>>();
+    let g = g.collect::<Vec<_>>();
+    // Too few points per job could not fill a round of batch-affine additions.
+    let xy = maps.filter(|_| part_len >= BATCH_SIZE).map(|maps| {
+        let xy = g.par_iter().map(|g| (maps.to_xy)(g)).collect::<Vec<_>>();
+        (maps, xy)
+    });
+
+    let sums = (0..windows * parts)
+        .into_par_iter()
+        .map(|job| {
+            let (cur, part) = (job / parts, job % parts);
+            let start = (part * part_len).min(n);
+            let end = (start + part_len).min(n);
+            let s = s[start..end].iter();
+            let buckets = match &xy {
+                Some((maps, xy)) => {
+                    let mut buckets = AffineBuckets::new(*maps, 1 << (c - 1));
+                    for (s, p) in s.zip(xy[start..end].iter()) {
+                        if let Some(p) = p {
+                            buckets.add(signed_digit(s, cur, c), p);
+                        }
+                    }
+                    buckets.finish()
+                }
+                None => {
+                    let mut buckets = vec![G::Projective::zero(); 1 << (c - 1)];
+                    for (s, g) in s.zip(g[start..end].iter()) {
+                        add_to_bucket(
+                            &mut buckets,
+                            signed_digit(s, cur, c),
+                            *g,
+                        );
+                    }
+                    buckets
+                }
+            };
+            sum_buckets(&buckets)
+        })
+        .collect::<Vec<_>>();
+
+    let mut acc = G::Projective::zero();
+    for window in sums.chunks(parts).rev() {
+        for _ in 0..c {
+            acc.double();
+        }
+        for part in window {
+            acc.add_assign(part);
+        }
+    }
+    acc
+}
+
+/// Computes `\sum_i s_i g_i` as [`multiexp`] does, running in the pool chosen
+/// by `par`.
+///
+/// [`multiexp`]: fn.multiexp.html
+pub fn multiexp_in<
+    'a,
+    G: CurveAffine,
+    IB: IntoIterator<Item = &'a G> + Send,
+    IS: IntoIterator<Item = &'a G::Scalar> + Send,
+>(
+    par: Parallelism,
+    g: IB,
+    s: IS,
+) -> G::Projective
+where
+    IB::IntoIter: ExactSizeIterator + Clone + Send,
+    IS::IntoIter: ExactSizeIterator + Clone + Send,
+{
+    par.install(|| multiexp(g, s))
+}
+
+/// Computes `\sum_i s_i g_i` for projective bases `g`.
+///
+/// The bases are first converted to affine in batches, which costs little next
+/// to the multi-exponentiation itself.
+///
+/// # Panics
+///
+/// Panics if `g` and `s` differ in length.
+pub fn multiexp_projective<G: CurveProjective>(g: &[G], s: &[G::Scalar]) -> G {
+    assert_eq!(g.len(), s.len());
+    let g = batch_into_affine(g.to_vec());
+    multiexp::<G::Affine, _, _>(g.iter(), s.iter())
+}
+
+/// Computes `\sum_i s_i g_i` from pairs `(g_i, s_i)`.
+pub fn multiexp_pairs<
+    G: CurveAffine,
+    I: IntoIterator<Item = (G, G::Scalar)>,
+>(
+    pairs: I,
+) -> G::Projective {
+    let (g, s): (Vec<_>, Vec<_>) = pairs.into_iter().unzip();
+    multiexp(g.iter(), s.iter())
+}
+
+/// Bases prepared for many multi-exponentiations, as by [`multiexp`].
+///
+/// For a window size `c`, each base `g_i` is stored along with `2^{jc} g_i` for
+/// every window `j`. A multi-exponentiation then adds the points for all
+/// windows into a single set of buckets, with no doublings, and sums the
+/// buckets once rather than once per window. This takes `\lceil 256 / c \rceil`
+/// points of memory per base: around 20 to 25 for thousands of bases.
+///
+/// [`multiexp`]: fn.multiexp.html
+pub struct PrecomputedBases<G: CurveAffine> {
+    /// The window size.
+    c: usize,
+    /// The number of windows, and so of points stored per base.
+    windows: usize,
+    /// `2^{jc} g_i` at index `i * windows + j`.
+    table: Vec<G>,
+}
+
+impl<G: CurveAffine> PrecomputedBases<G> {
+    /// Prepares `bases`, choosing the window size from their number and the
+    /// number of threads available.
+    pub fn new(bases: &[G]) -> Self {
+        let threads = rayon::current_num_threads().max(1);
+        let n = bases.len().div_ceil(threads);
+        let bits = <G::Engine as ScalarEngine>::Fr::NUM_BITS as usize;
+        // Each thread adds its points once per window, and all buckets are
+        // summed once.
+        let c = (1..=bits.min(24))
+            .min_by_key(|&c| n * signed_windows(bits, c) + (1 << c))
+            .expect("the range of window sizes is not empty");
+        Self::with_window(bases, c)
+    }
+
+    /// Prepares `bases` with a window size of `c` bits.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `c` is not between 1 and 24.
+    pub fn with_window(bases: &[G], c: usize) -> Self {
+        assert!((1..=24).contains(&c), "window size out of range");
+        let bits = <G::Engine as ScalarEngine>::Fr::NUM_BITS as usize;
+        let windows = signed_windows(bits, c);
+        let table = bases
+            .par_iter()
+            .flat_map(|g| {
+                let mut g = g.into_projective();
+                (0..windows)
+                    .map(|_| {
+                        let res = g;
+                        for _ in 0..c {
+                            g.double();
+                        }
+                        res
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        PrecomputedBases {
+            c,
+            windows,
+            table: batch_into_affine(table),
+        }
+    }
+
+    /// The number of bases.
+    pub fn len(&self) -> usize {
+        self.table.len() / self.windows
+    }
+
+    /// Whether there are no bases.
+    pub fn is_empty(&self) -> bool {
+        self.table.is_empty()
+    }
+
+    /// The window size.
+    pub fn window(&self) -> usize {
+        self.c
+    }
+
+    /// Computes `\sum_i s_i g_{start + i}`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are fewer than `start + s.len()` bases.
+    pub fn multiexp(&self, start: usize, s: &[G::Scalar]) -> G::Projective {
+        assert!(start + s.len() <= self.len(), "not enough bases");
+        let (c, windows) = (self.c, self.windows);
+        let parts = rayon::current_num_threads().max(1);
+        let part_len = s.len().div_ceil(parts).max(1);
+        s.par_chunks(part_len)
+            .enumerate()
+            .map(|(part, s)| {
+                let offset = (start + part * part_len) * windows;
+                let mut buckets = vec![G::Projective::zero(); 1 << (c - 1)];
+                for (i, s) in s.iter().enumerate() {
+                    let s = s.into_repr();
+                    let points = &self.table[offset + i * windows..];
+                    for (j, g) in points[..windows].iter().enumerate() {
+                        add_to_bucket(&mut buckets, signed_digit(&s, j, c), g);
+                    }
+                }
+                sum_buckets(&buckets)
+            })
+            .reduce(G::Projective::zero, |mut a, b| {
+                a.add_assign(&b);
+                a
+            })
+    }
+
+    /// Computes `\sum_i s_i g_{start + i}` as [`multiexp`] does, running in the
+    /// pool chosen by `par`.
+    ///
+    /// [`multiexp`]: #method.multiexp
+    pub fn multiexp_in(
+        &self,
+        par: Parallelism,
+        start: usize,
+        s: &[G::Scalar],
+    ) -> G::Projective {
+        par.install(|| self.multiexp(start, s))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ro::RO;
+    use pairing::bls12_381::{Fr, FrRepr, G1, G2};
+    use rayon::ThreadPoolBuilder;
+    use sha3::Sha3_256;
+
+    /// Checks [`multiexp_batch_affine`] and [`multiexp`] on `n` points that are
+    /// small multiples of one point, including its negations and the point at
+    /// infinity, so that buckets see doublings and cancellations. A single
+    /// thread gives each job enough points for batch-affine rounds.
+    fn check_batch_affine<G: CurveProjective<Scalar = Fr>>(
+        n: usize,
+        distinct_scalars: usize,
+    ) where
+        G::Affine: Coordinates,
+    {
+        let mut rng = Sha3_256::query(b"multiexp test").into_rng();
+        let p = G::random(&mut rng);
+        let scalars = (0..distinct_scalars)
+            .map(|_| Fr::random(&mut rng))
+            .collect::<Vec<_>>();
+        let s = (0..n)
+            .map(|i| scalars[i % distinct_scalars])
+            .collect::<Vec<_>>();
+        let k = |i: usize| (i % 37) as i64 - 18;
+        let g = (0..n)
+            .map(|i| {
+                let mut g = p;
+                g.mul_assign(
+                    Fr::from_repr((k(i).unsigned_abs()).into()).unwrap(),
+                );
+                if k(i) < 0 {
+                    g.negate();
+                }
+                g.into_affine()
+            })
+            .collect::<Vec<_>>();
+        let mut expected = Fr::zero();
+        for (i, s) in s.iter().enumerate() {
+            let mut t = Fr::from_repr((k(i).unsigned_abs()).into()).unwrap();
+            if k(i) < 0 {
+                t.negate();
+            }
+            t.mul_assign(s);
+            expected.add_assign(&t);
+        }
+        let mut expected_point = p;
+        expected_point.mul_assign(expected);
+        let pool = ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+        let res = pool.install(|| multiexp_batch_affine(g.iter(), s.iter()));
+        assert_eq!(res, expected_point);
+        assert_eq!(multiexp(g.iter(), s.iter()), expected_point);
+    }
+
+    #[test]
+    fn batch_affine_matches_scalar_multiplication() {
+        check_batch_affine::<G1>(3 * BATCH_SIZE, 3 * BATCH_SIZE);
+        check_batch_affine::<G2>(3 * BATCH_SIZE, 3 * BATCH_SIZE);
+    }
+
+    #[test]
+    fn batch_affine_handles_crowded_buckets() {
+        // With few distinct scalars, rounds fall back to mixed addition.
+        check_batch_affine::<G1>(2 * BATCH_SIZE, 2);
+        check_batch_affine::<G2>(2 * BATCH_SIZE, 2);
+    }
+
+    /// Computes `\sum_j d_j 2^{jc} p` for the signed digits `d_j` of `s`,
+    /// checking that each is in range.
+    fn recombine<G: CurveAffine<Scalar = Fr>>(
+        p: &G,
+        s: &Fr,
+        c: usize,
+    ) -> G::Projective {
+        let repr = s.into_repr();
+        let mut acc = G::Projective::zero();
+        for j in (0..signed_windows(Fr::NUM_BITS as usize, c)).rev() {
+            for _ in 0..c {
+                acc.double();
+            }
+            let digit = signed_digit(&repr, j, c);
+            assert!(digit.unsigned_abs() <= 1 << (c - 1));
+            let mut term = p.mul(
+                Fr::from_repr(FrRepr::from(digit.unsigned_abs() as u64))
+                    .unwrap(),
+            );
+            if digit < 0 {
+                term.negate();
+            }
+            acc.add_assign(&term);
+        }
+        acc
+    }
+
+    fn check_signed_digits<G: CurveProjective<Scalar = Fr>>() {
+        let mut rng = Sha3_256::query(b"signed digit test").into_rng();
+        let p = G::random(&mut rng).into_affine();
+        let mut minus_one = Fr::one();
+        minus_one.negate();
+        let mut minus_two = minus_one;
+        minus_two.sub_assign(&Fr::one());
+        for &c in [1, 2, 3, 7, 8, 13, 16, 24].iter() {
+            let edge = |v: u64| Fr::from_repr(FrRepr::from(v)).unwrap();
+            let mut scalars = vec![
+                Fr::zero(),
+                Fr::one(),
+                minus_one,
+                minus_two,
+                // Digits at the edges of a window.
+                edge((1 << (c - 1)) - 1),
+                edge(1 << (c - 1)),
+                edge((1 << c) - 1),
+                // Windows straddling limbs.
+                Fr::from_repr(FrRepr([u64::MAX, u64::MAX, u64::MAX, 1 << 62]))
+                    .unwrap(),
+            ];
+            scalars.extend((0..4).map(|_| Fr::random(&mut rng)));
+            for s in scalars.iter() {
+                assert_eq!(recombine(&p, s, c), p.mul(*s), "c = {}", c);
+            }
+        }
+    }
+
+    #[test]
+    fn signed_digits_match_scalar_multiplication() {
+        check_signed_digits::<G1>();
+        check_signed_digits::<G2>();
+    }
+
+    /// Computes `\sum_i s_i g_i` one term at a time.
+    fn naive<G: CurveAffine>(g: &[G], s: &[G::Scalar]) -> G::Projective {
+        let mut acc = G::Projective::zero();
+        for (g, s) in g.iter().zip(s.iter()) {
+            acc.add_assign(&g.mul(*s));
+        }
+        acc
+    }
+
+    fn check_entry_points<G: CurveProjective<Scalar = Fr>>() {
+        let mut rng = Sha3_256::query(b"multiexp entry points").into_rng();
+        let n = 50;
+        let projective =
+            (0..n).map(|_| G::random(&mut rng)).collect::<Vec<_>>();
+        let g = projective
+            .iter()
+            .map(|g| g.into_affine())
+            .collect::<Vec<_>>();
+        let s = (0..n).map(|_| Fr::random(&mut rng)).collect::<Vec<_>>();
+        let expected = naive(&g, &s);
+        assert_eq!(multiexp(g.iter(), s.iter()), expected);
+        assert_eq!(
+            multiexp_in(Parallelism::Sequential, g.iter(), s.iter()),
+            expected
+        );
+        assert_eq!(multiexp_projective(&projective, &s), expected);
+        assert_eq!(
+            multiexp_pairs(g.iter().copied().zip(s.iter().copied())),
+            expected
+        );
+        assert!(multiexp::<G::Affine, _, _>(&[], &[]).is_zero());
+
+        let precomputed = PrecomputedBases::new(&g);
+        assert_eq!(precomputed.len(), n);
+        assert_eq!(precomputed.multiexp(0, &s), expected);
+        for &c in [1, 5, 16].iter() {
+            let precomputed = PrecomputedBases::with_window(&g, c);
+            assert_eq!(precomputed.window(), c);
+            assert_eq!(precomputed.multiexp(0, &s), expected);
+            // A window of the bases, starting part way through.
+            assert_eq!(
+                precomputed.multiexp_in(Parallelism::Sequential, 7, &s[..20]),
+                naive(&g[7..27], &s[..20])
+            );
+            assert!(precomputed.multiexp(n, &[]).is_zero());
+        }
+    }
+
+    #[test]
+    fn entry_points_match_naive_sum() {
+        check_entry_points::<G1>();
+        check_entry_points::<G2>();
+    }
+
+    #[test]
+    #[should_panic(expected = "not enough bases")]
+    fn precomputed_bases_check_length() {
+        let g = vec![G1Affine::one(); 3];
+        PrecomputedBases::with_window(&g, 4).multiexp(1, &[Fr::one(); 3]);
+    }
+}
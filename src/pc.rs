@@ -0,0 +1,598 @@
+//! Sonic's polynomial commitment scheme [1] over an updatable reference string.
+//!
+//! A polynomial `f` with degree bound `max` is committed to as
+//! `g^{\alpha x^{d - max} f(x)}`. As the SRS lacks `g^\alpha`, this also
+//! enforces that `f` has no `X^{max - d}` term.
+//!
+//! [1] Mary Maller, Sean Bowe, Markulf Kohlweiss and Sarah Meiklejohn. Sonic:
+//! Zero-knowledge SNARKs from linear-size universal and updatable structured
+//! reference strings. CCS 2019.
+
+use crate::multiexp::multiexp;
+use crate::usrs::USRS;
+use crate::util::random_scalars;
+use ff::{Field, PrimeField};
+use group::{CurveAffine, CurveProjective};
+use pairing::{Engine, PairingCurveAffine};
+use rand::{CryptoRng, Rng};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+
+/// An error encountered while committing to or opening a polynomial.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CommitmentError {
+    /// The degree bound exceeds the dimension of the SRS.
+    MaxDegree(usize),
+    /// A term requires a power of `x` outside of the SRS.
+    OutOfRange(isize),
+    /// A nonzero term falls on `g^\alpha`, which the SRS does not contain.
+    ConstantTerm,
+    /// An SRS element is not a valid encoding of a point in the prime order
+    /// subgroup.
+    InvalidPoint,
+}
+
+impl fmt::Display for CommitmentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommitmentError::MaxDegree(max) => {
+                write!(f, "degree bound {} exceeds the SRS dimension", max)
+            }
+            CommitmentError::OutOfRange(i) => {
+                write!(f, "x^{} is not in the SRS", i)
+            }
+            CommitmentError::ConstantTerm => {
+                write!(f, "polynomial has a term at the missing g^alpha")
+            }
+            CommitmentError::InvalidPoint => {
+                write!(f, "SRS contains an invalid point")
+            }
+        }
+    }
+}
+
+impl Error for CommitmentError {}
+
+/// A Laurent polynomial `\sum_i c_i X^{offset + i}`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LaurentPolynomial<F: PrimeField> {
+    /// The power of `X` of the first coefficient.
+    pub offset: isize,
+    /// The coefficients, in order of increasing power.
+    pub coeffs: Vec<F>,
+}
+
+/// Raises `z` to a possibly negative power.
+///
+/// # Panics
+///
+/// Panics if `z` is zero and `e` is negative.
+fn pow<F: Field>(z: F, e: isize) -> F {
+    if e >= 0 {
+        z.pow([e as u64])
+    } else {
+        z.inverse()
+            .expect("negative powers require a nonzero point")
+            .pow([(-e) as u64])
+    }
+}
+
+impl<F: PrimeField> LaurentPolynomial<F> {
+    /// Creates a new polynomial.
+    pub fn new(offset: isize, coeffs: Vec<F>) -> Self {
+        LaurentPolynomial { offset, coeffs }
+    }
+
+    /// Evaluates the polynomial at `z`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `z` is zero and the polynomial has negative powers.
+    pub fn evaluate(&self, z: F) -> F {
+        let mut res = F::zero();
+        for c in self.coeffs.iter().rev() {
+            res.mul_assign(&z);
+            res.add_assign(c);
+        }
+        res.mul_assign(&pow(z, self.offset));
+        res
+    }
+
+    /// Adds `c * other` to this polynomial.
+    pub fn add_scaled(&mut self, c: F, other: &Self) {
+        if other.coeffs.is_empty() {
+            return;
+        }
+        let offset = self.offset.min(other.offset);
+        let end = (self.offset + self.coeffs.len() as isize)
+            .max(other.offset + other.coeffs.len() as isize);
+        let mut coeffs = vec![F::zero(); (end - offset) as usize];
+        let start = (self.offset - offset) as usize;
+        coeffs[start..start + self.coeffs.len()].copy_from_slice(&self.coeffs);
+        let start = (other.offset - offset) as usize;
+        for (a, b) in coeffs[start..].iter_mut().zip(other.coeffs.iter()) {
+            let mut b = *b;
+            b.mul_assign(&c);
+            a.add_assign(&b);
+        }
+        self.offset = offset;
+        self.coeffs = coeffs;
+    }
+
+    /// Returns `f(z)`, and the witness polynomial `(f(X) - f(z)) / (X - z)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `z` is zero and the polynomial has negative powers.
+    pub fn divide(&self, z: F) -> (F, Self) {
+        let v = self.evaluate(z);
+        // f(X) - f(z) = X^o g(X), for a polynomial g with g(z) = 0.
+        let o = self.offset.min(0);
+        let start = (self.offset - o) as usize;
+        let len = (start + self.coeffs.len()).max((1 - o) as usize);
+        let mut g = vec![F::zero(); len];
+        g[start..start + self.coeffs.len()].copy_from_slice(&self.coeffs);
+        g[(-o) as usize].sub_assign(&v);
+        // Synthetic division of g by X - z.
+        let mut q = vec![F::zero(); len - 1];
+        let mut carry = F::zero();
+        for i in (1..len).rev() {
+            carry.mul_assign(&z);
+            carry.add_assign(&g[i]);
+            q[i - 1] = carry;
+        }
+        (v, LaurentPolynomial::new(o, q))
+    }
+}
+
+/// An opening of a committed polynomial at a point.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Opening<E: Engine> {
+    /// The polynomial's value at the point.
+    pub value: E::Fr,
+    /// The commitment to the witness polynomial.
+    pub witness: E::G1Affine,
+}
+
+/// An opening of several polynomials with the same degree bound at the same
+/// point.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BatchOpening<E: Engine> {
+    /// The polynomials' values at the point.
+    pub values: Vec<E::Fr>,
+    /// The commitment to the witness polynomial of their random linear
+    /// combination.
+    pub witness: E::G1Affine,
+}
+
+/// A claimed opening of a commitment, for batch verification.
+pub struct OpeningClaim<'a, E: Engine> {
+    /// The degree bound of the committed polynomial.
+    pub max: usize,
+    /// The commitment.
+    pub commitment: E::G1Affine,
+    /// The point of evaluation.
+    pub point: E::Fr,
+    /// The opening.
+    pub opening: &'a Opening<E>,
+}
+
+/// The index of `x^i` in the vectors of an SRS of dimension `d`.
+fn index(d: usize, i: isize) -> Result<usize, CommitmentError> {
+    let d = d as isize;
+    if i < -d || i > d {
+        Err(CommitmentError::OutOfRange(i))
+    } else {
+        Ok((i + d) as usize)
+    }
+}
+
+/// Commits to `f` as a plain polynomial `g^{f(x)}`, without a degree bound.
+fn commit_plain<E: Engine, K: CommitmentKey<E> + ?Sized>(
+    key: &K,
+    f: &LaurentPolynomial<E::Fr>,
+) -> Result<E::G1Affine, CommitmentError> {
+    let d = key.dimension();
+    let bases = (0..f.coeffs.len())
+        .map(|i| key.g_x(index(d, f.offset + i as isize)?))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(multiexp(bases.iter(), f.coeffs.iter()).into_affine())
+}
+
+/// The SRS elements used by the commitment scheme, whether held in memory as a
+/// [`USRS`], or read from elsewhere.
+///
+/// Elements are indexed as in the vectors of a [`USRS`]; that is, `x^i` is at
+/// index `i + d`. Keys that decode elements on demand report an element that
+/// fails to decode as [`CommitmentError::InvalidPoint`]; the verification
+/// methods then return `false`.
+///
+/// [`USRS`]: ../usrs/struct.USRS.html
+/// [`CommitmentError::InvalidPoint`]: enum.CommitmentError.html#variant.InvalidPoint
+pub trait CommitmentKey<E: Engine> {
+    /// The dimension of the SRS.
+    fn dimension(&self) -> usize;
+    /// g^{x^i} at index i + d
+    fn g_x(&self, index: usize) -> Result<E::G1Affine, CommitmentError>;
+    /// g^{x^i \alpha} at index i + d
+    fn g_ax(&self, index: usize) -> Result<E::G1Affine, CommitmentError>;
+    /// h^{x^i} at index i + d
+    fn h_x(&self, index: usize) -> Result<E::G2Affine, CommitmentError>;
+    /// h^{x^i \alpha} at index i + d
+    fn h_ax(&self, index: usize) -> Result<E::G2Affine, CommitmentError>;
+
+    /// Commits to `f`, which has degree at most `max`.
+    fn commit(
+        &self,
+        max: usize,
+        f: &LaurentPolynomial<E::Fr>,
+    ) -> Result<E::G1Affine, CommitmentError> {
+        let d = self.dimension();
+        if max > d {
+            return Err(CommitmentError::MaxDegree(max));
+        }
+        let shift = d as isize - max as isize + f.offset;
+        let mut bases = Vec::with_capacity(f.coeffs.len());
+        let mut scalars = Vec::with_capacity(f.coeffs.len());
+        for (i, c) in f.coeffs.iter().enumerate() {
+            if c.is_zero() {
+                continue;
+            }
+            let j = shift + i as isize;
+            if j == 0 {
+                return Err(CommitmentError::ConstantTerm);
+            }
+            bases.push(self.g_ax(index(d, j)?)?);
+            scalars.push(*c);
+        }
+        Ok(multiexp(bases.iter(), scalars.iter()).into_affine())
+    }
+
+    /// Opens `f` at the point `z`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `z` is zero and `f` has negative powers.
+    fn open(
+        &self,
+        f: &LaurentPolynomial<E::Fr>,
+        z: E::Fr,
+    ) -> Result<Opening<E>, CommitmentError> {
+        let (value, w) = f.divide(z);
+        Ok(Opening {
+            value,
+            witness: commit_plain(self, &w)?,
+        })
+    }
+
+    /// Verifies that `commitment`, to a polynomial of degree at most `max`,
+    /// opens to `opening.value` at `z`.
+    fn verify_opening(
+        &self,
+        max: usize,
+        commitment: &E::G1Affine,
+        z: E::Fr,
+        opening: &Opening<E>,
+    ) -> bool {
+        let d = self.dimension();
+        if max > d {
+            return false;
+        }
+        let (h_ax, h_a, h_max) =
+            match (self.h_ax(d + 1), self.h_ax(d), self.h_x(max)) {
+                (Ok(h_ax), Ok(h_a), Ok(h_max)) => (h_ax, h_a, h_max),
+                _ => return false,
+            };
+        // e(W, h^{\alpha x}) e(g^v W^{-z}, h^\alpha) = e(F, h^{x^{max - d}})
+        let mut gvwz = opening.witness.mul(z);
+        gvwz.negate();
+        gvwz.add_assign(&E::G1Affine::one().mul(opening.value));
+        let mut neg_f = *commitment;
+        neg_f.negate();
+        let table = [
+            (opening.witness.prepare(), h_ax.prepare()),
+            (gvwz.into_affine().prepare(), h_a.prepare()),
+            (neg_f.prepare(), h_max.prepare()),
+        ];
+        let table_ref = table.iter().map(|(a, b)| (a, b)).collect::<Vec<_>>();
+        E::final_exponentiation(&E::miller_loop(&table_ref[..])).unwrap()
+            == E::Fqk::one()
+    }
+
+    /// Verifies many openings at once, using a random linear combination of the
+    /// pairing checks.
+    ///
+    /// This costs one pairing per distinct degree bound, plus two.
+    fn verify_openings<R: Rng + CryptoRng + ?Sized>(
+        &self,
+        claims: &[OpeningClaim<E>],
+        rng: &mut R,
+    ) -> bool {
+        let d = self.dimension();
+        if claims.iter().any(|c| c.max > d) {
+            return false;
+        }
+        let (h_ax, h_a) = match (self.h_ax(d + 1), self.h_ax(d)) {
+            (Ok(h_ax), Ok(h_a)) => (h_ax, h_a),
+            _ => return false,
+        };
+        let rnd = random_scalars::<E::Fr, _>(claims.len(), rng);
+        let ws = claims.iter().map(|c| c.opening.witness).collect::<Vec<_>>();
+        // \sum_i -r_i z_i W_i + g^{\sum_i r_i v_i}
+        let mut v = E::Fr::zero();
+        let neg_rz = claims
+            .iter()
+            .zip(rnd.iter())
+            .map(|(c, r)| {
+                let mut rv = c.opening.value;
+                rv.mul_assign(r);
+                v.add_assign(&rv);
+                let mut rz = c.point;
+                rz.mul_assign(r);
+                rz.negate();
+                rz
+            })
+            .collect::<Vec<_>>();
+        let mut gvwz = multiexp(ws.iter(), neg_rz.iter());
+        gvwz.add_assign(&E::G1Affine::one().mul(v));
+        let mut table = vec![
+            (
+                multiexp(ws.iter(), rnd.iter()).into_affine().prepare(),
+                h_ax.prepare(),
+            ),
+            (gvwz.into_affine().prepare(), h_a.prepare()),
+        ];
+        // Commitments sharing a degree bound share a pairing.
+        let mut by_max = BTreeMap::new();
+        for (c, r) in claims.iter().zip(rnd.iter()) {
+            let mut neg_r = *r;
+            neg_r.negate();
+            let (fs, rs) =
+                by_max.entry(c.max).or_insert((Vec::new(), Vec::new()));
+            fs.push(c.commitment);
+            rs.push(neg_r);
+        }
+        for (max, (fs, rs)) in by_max {
+            let h_max = match self.h_x(max) {
+                Ok(h_max) => h_max,
+                Err(_) => return false,
+            };
+            table.push((
+                multiexp(fs.iter(), rs.iter()).into_affine().prepare(),
+                h_max.prepare(),
+            ));
+        }
+        let table_ref = table.iter().map(|(a, b)| (a, b)).collect::<Vec<_>>();
+        E::final_exponentiation(&E::miller_loop(&table_ref[..])).unwrap()
+            == E::Fqk::one()
+    }
+
+    /// Opens several polynomials at the same point `z`, with a single witness.
+    ///
+    /// `gamma` must be chosen after the commitments are fixed, for instance as
+    /// the verifier's challenge, or by hashing the commitments.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `z` is zero and a polynomial has negative powers.
+    fn open_batch(
+        &self,
+        fs: &[LaurentPolynomial<E::Fr>],
+        z: E::Fr,
+        gamma: E::Fr,
+    ) -> Result<BatchOpening<E>, CommitmentError> {
+        let mut combined = LaurentPolynomial::new(0, Vec::new());
+        let mut gamma_i = E::Fr::one();
+        for f in fs.iter() {
+            combined.add_scaled(gamma_i, f);
+            gamma_i.mul_assign(&gamma);
+        }
+        let (_, w) = combined.divide(z);
+        Ok(BatchOpening {
+            values: fs.iter().map(|f| f.evaluate(z)).collect(),
+            witness: commit_plain(self, &w)?,
+        })
+    }
+
+    /// Verifies an opening created with [`open_batch`] of commitments to
+    /// polynomials of degree at most `max`.
+    ///
+    /// [`open_batch`]: #method.open_batch
+    fn verify_batch_opening(
+        &self,
+        max: usize,
+        commitments: &[E::G1Affine],
+        z: E::Fr,
+        gamma: E::Fr,
+        opening: &BatchOpening<E>,
+    ) -> bool {
+        if commitments.len() != opening.values.len() {
+            return false;
+        }
+        let mut gammas = Vec::with_capacity(commitments.len());
+        let mut gamma_i = E::Fr::one();
+        let mut value = E::Fr::zero();
+        for v in opening.values.iter() {
+            let mut tmp = *v;
+            tmp.mul_assign(&gamma_i);
+            value.add_assign(&tmp);
+            gammas.push(gamma_i);
+            gamma_i.mul_assign(&gamma);
+        }
+        let commitment =
+            multiexp(commitments.iter(), gammas.iter()).into_affine();
+        self.verify_opening(
+            max,
+            &commitment,
+            z,
+            &Opening {
+                value,
+                witness: opening.witness,
+            },
+        )
+    }
+}
+
+impl<E: Engine> CommitmentKey<E> for USRS<E> {
+    fn dimension(&self) -> usize {
+        self.d
+    }
+
+    fn g_x(&self, index: usize) -> Result<E::G1Affine, CommitmentError> {
+        Ok(self.g_x[index])
+    }
+
+    fn g_ax(&self, index: usize) -> Result<E::G1Affine, CommitmentError> {
+        Ok(self.g_ax[index])
+    }
+
+    fn h_x(&self, index: usize) -> Result<E::G2Affine, CommitmentError> {
+        Ok(self.h_x[index])
+    }
+
+    fn h_ax(&self, index: usize) -> Result<E::G2Affine, CommitmentError> {
+        Ok(self.h_ax[index])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ro::RO;
+    use pairing::bls12_381::{Bls12, Fr};
+    use sha3::Sha3_256;
+
+    const D: usize = 4;
+
+    fn setup() -> (USRS<Bls12>, impl Rng + CryptoRng) {
+        let mut rng = Sha3_256::query(b"pc test").into_rng();
+        let srs = USRS::new(D).permute(&rng.gen());
+        (srs, rng)
+    }
+
+    fn random_poly<R: Rng + ?Sized>(
+        offset: isize,
+        len: usize,
+        rng: &mut R,
+    ) -> LaurentPolynomial<Fr> {
+        LaurentPolynomial::new(
+            offset,
+            (0..len).map(|_| Fr::random(rng)).collect(),
+        )
+    }
+
+    #[test]
+    fn divide_gives_quotient() {
+        let (_, mut rng) = setup();
+        let f = random_poly(-2, 5, &mut rng);
+        let (z, y) = (Fr::random(&mut rng), Fr::random(&mut rng));
+        let (v, w) = f.divide(z);
+        assert_eq!(v, f.evaluate(z));
+        // f(y) - f(z) = (y - z) w(y)
+        let mut lhs = f.evaluate(y);
+        lhs.sub_assign(&v);
+        let mut rhs = y;
+        rhs.sub_assign(&z);
+        rhs.mul_assign(&w.evaluate(y));
+        assert_eq!(lhs, rhs);
+    }
+
+    #[test]
+    fn open_verifies() {
+        let (srs, mut rng) = setup();
+        // Powers -1 to 1, shifted by d - max = 2, avoid the missing g^\alpha.
+        let f = random_poly(-1, 3, &mut rng);
+        let c = srs.commit(2, &f).unwrap();
+        let z = Fr::random(&mut rng);
+        let opening = srs.open(&f, z).unwrap();
+        assert!(srs.verify_opening(2, &c, z, &opening));
+        // A different value, point or degree bound is rejected.
+        let mut wrong = opening.clone();
+        wrong.value.add_assign(&Fr::one());
+        assert!(!srs.verify_opening(2, &c, z, &wrong));
+        assert!(!srs.verify_opening(2, &c, Fr::random(&mut rng), &opening));
+        assert!(!srs.verify_opening(3, &c, z, &opening));
+        assert!(!srs.verify_opening(D + 1, &c, z, &opening));
+    }
+
+    #[test]
+    fn commit_enforces_bounds() {
+        let (srs, mut rng) = setup();
+        assert_eq!(
+            srs.commit(D + 1, &random_poly(0, 1, &mut rng)),
+            Err(CommitmentError::MaxDegree(D + 1))
+        );
+        // With max = d, the constant term falls on g^\alpha.
+        assert_eq!(
+            srs.commit(D, &random_poly(0, 2, &mut rng)),
+            Err(CommitmentError::ConstantTerm)
+        );
+        assert_eq!(
+            srs.commit(D, &random_poly(1, D + 1, &mut rng)),
+            Err(CommitmentError::OutOfRange(D as isize + 1))
+        );
+        // Zero coefficients may fall anywhere.
+        let zero = LaurentPolynomial::new(0, vec![Fr::zero()]);
+        assert!(srs.commit(D, &zero).is_ok());
+    }
+
+    #[test]
+    fn verify_openings_batches() {
+        let (srs, mut rng) = setup();
+        let maxes = [1, 2, 2];
+        let fs = maxes
+            .iter()
+            .map(|max| random_poly(1, *max, &mut rng))
+            .collect::<Vec<_>>();
+        let cs = maxes
+            .iter()
+            .zip(fs.iter())
+            .map(|(max, f)| srs.commit(*max, f).unwrap())
+            .collect::<Vec<_>>();
+        let zs = (0..3).map(|_| Fr::random(&mut rng)).collect::<Vec<_>>();
+        let mut openings = fs
+            .iter()
+            .zip(zs.iter())
+            .map(|(f, z)| srs.open(f, *z).unwrap())
+            .collect::<Vec<_>>();
+        let claims = |openings: &[Opening<Bls12>]| -> bool {
+            let claims = (0..3)
+                .map(|i| OpeningClaim {
+                    max: maxes[i],
+                    commitment: cs[i],
+                    point: zs[i],
+                    opening: &openings[i],
+                })
+                .collect::<Vec<_>>();
+            srs.verify_openings(
+                &claims,
+                &mut Sha3_256::query(b"pc batch").into_rng(),
+            )
+        };
+        assert!(claims(&openings));
+        openings[1].value.add_assign(&Fr::one());
+        assert!(!claims(&openings));
+    }
+
+    #[test]
+    fn open_batch_verifies() {
+        let (srs, mut rng) = setup();
+        let fs = (0..3)
+            .map(|_| random_poly(-1, 3, &mut rng))
+            .collect::<Vec<_>>();
+        let cs = fs
+            .iter()
+            .map(|f| srs.commit(2, f).unwrap())
+            .collect::<Vec<_>>();
+        let (z, gamma) = (Fr::random(&mut rng), Fr::random(&mut rng));
+        let mut opening = srs.open_batch(&fs, z, gamma).unwrap();
+        for (f, v) in fs.iter().zip(opening.values.iter()) {
+            assert_eq!(*v, f.evaluate(z));
+        }
+        assert!(srs.verify_batch_opening(2, &cs, z, gamma, &opening));
+        assert!(!srs.verify_batch_opening(2, &cs[..2], z, gamma, &opening));
+        opening.values[2].add_assign(&Fr::one());
+        assert!(!srs.verify_batch_opening(2, &cs, z, gamma, &opening));
+    }
+}
@@ -1,10 +1,16 @@
+use crate::multiexp::multiexp_pairs;
 use crate::ro::RO;
-use crate::util::Split;
+use crate::serialize::{
+    read_point, read_scalar, write_point, write_scalar, Serializable,
+};
+use crate::usrs::ImportError;
+use crate::util::{random_small_scalars, Parallelism, Split};
 use ff::{Field, PrimeField};
 use group::{CurveAffine, CurveProjective};
 use rand::distributions::{Distribution, Standard};
 use rand::{CryptoRng, Rng};
 use rayon::prelude::*;
+use std::io::{self, Read, Write};
 use std::marker::PhantomData;
 
 pub trait Relation {
@@ -29,13 +35,11 @@ pub trait NIZK: Relation {
     /// May panic if [`check(x, w)`] is `false`.
     ///
     /// [`check(x, w)`]: #method.check
-    fn prove<R: Split + Rng + CryptoRng>(
+    fn prove<R: Split + Rng + CryptoRng + Send>(
         x: &Self::X,
         w: &Self::W,
         rng: &mut R,
-    ) -> Self::Proof
-    where
-        R: Send;
+    ) -> Self::Proof;
     /// Verifies a proof against a statement.
     ///
     /// Must return `true` for valid proofs, and false for unsatisfiable
@@ -43,9 +47,51 @@ pub trait NIZK: Relation {
     fn verify(x: &Self::X, pi: &Self::Proof) -> bool;
 }
 
-/// A non-interactive zero-knowledge 'proof' for statements in which knowledge of the statement is
-/// sufficient to prove knowledge of the witness. The knowledge of exponent assumption is an
-/// instance of an implicit NIZK.
+/// A NIZK whose proofs have a canonical, compact encoding.
+///
+/// Unlike [`Serializable`], the encoding may rely on the proof system's
+/// parameters, such as a fixed number of repetitions, rather than describing
+/// the proof's shape.
+///
+/// [`Serializable`]: ../serialize/trait.Serializable.html
+pub trait SerializableNIZK: NIZK {
+    /// Writes the canonical encoding of a proof.
+    fn write_proof<W: Write>(pi: &Self::Proof, out: W) -> io::Result<()>;
+    /// Reads the canonical encoding of a proof.
+    fn read_proof<R: Read>(inp: R) -> Result<Self::Proof, ImportError>;
+
+    /// The canonical encoding of a proof, e.g. for hashing.
+    fn proof_bytes(pi: &Self::Proof) -> Vec<u8> {
+        let mut res = Vec::new();
+        Self::write_proof(pi, &mut res)
+            .expect("writing to a vector does not fail");
+        res
+    }
+}
+
+/// A NIZK whose proofs can be verified together.
+///
+/// The default verifies each proof in turn; implementations may instead fold
+/// all proofs into a single randomised check, which accepts a batch with an
+/// invalid proof only with negligible probability.
+pub trait BatchVerify: NIZK {
+    /// Verifies many proofs, returning whether all are valid for their
+    /// statements.
+    fn batch_verify<R: Rng + CryptoRng + ?Sized>(
+        instances: &[(Self::X, &Self::Proof)],
+        _: &mut R,
+    ) -> bool
+    where
+        Self::X: Sync,
+        Self::Proof: Sync,
+    {
+        instances.par_iter().all(|(x, pi)| Self::verify(x, pi))
+    }
+}
+
+/// A non-interactive zero-knowledge 'proof' for statements in which knowledge
+/// of the statement is sufficient to prove knowledge of the witness. The
+/// knowledge of exponent assumption is an instance of an implicit NIZK.
 pub struct ImplicitNIZK<X, W, H>(PhantomData<(X, W, H)>);
 
 impl<X, W, H> Relation for ImplicitNIZK<X, W, H> {
@@ -65,7 +111,6 @@ impl<X, W, H> NIZK for ImplicitNIZK<X, W, H> {
         _: &Self::W,
         _: &mut R,
     ) -> Self::Proof {
-        ()
     }
 
     fn verify(_: &Self::X, _: &Self::Proof) -> bool {
@@ -73,32 +118,177 @@ impl<X, W, H> NIZK for ImplicitNIZK<X, W, H> {
     }
 }
 
+/// Implicit proofs are empty.
+impl<X, W, H> SerializableNIZK for ImplicitNIZK<X, W, H> {
+    fn write_proof<Wr: Write>(_: &Self::Proof, _: Wr) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn read_proof<R: Read>(_: R) -> Result<Self::Proof, ImportError> {
+        Ok(())
+    }
+}
+
+impl<X, W, H> BatchVerify for ImplicitNIZK<X, W, H> {}
+
 /// Fischlin's transform [1] applied to a given sigma protocol.
 ///
-/// [1] Marc Fischlin. Communication-efficient non-interactive proofs of knowledge with online
-/// extractors. CRYPTO 2005.
-pub struct FischlinTransform<S: SigmaProtocol, H: RO + ?Sized>(
-    PhantomData<(S, H)>,
-);
+/// The parameters default to [`FischlinDefault`], which this crate has always
+/// used; pick a preset such as [`Fischlin128`] for a specific security level.
+/// The transform is only a proof of knowledge for protocols with
+/// [`UniqueResponses`].
+///
+/// [1] Marc Fischlin. Communication-efficient non-interactive proofs of
+/// knowledge with online extractors. CRYPTO 2005.
+///
+/// [`UniqueResponses`]: trait.UniqueResponses.html
+/// [`FischlinDefault`]: struct.FischlinDefault.html
+/// [`Fischlin128`]: struct.Fischlin128.html
+pub struct FischlinTransform<
+    S: SigmaProtocol,
+    H: RO + ?Sized,
+    P: FischlinParameters = FischlinDefault,
+>(PhantomData<(S, P, H)>);
+
+/// The parameters of Fischlin's transform.
+///
+/// The names of the parameters in [1] are given in brackets.
+///
+/// [1] Marc Fischlin. Communication-efficient non-interactive proofs of
+/// knowledge with online extractors. CRYPTO 2005.
+pub trait FischlinParameters {
+    /// The number of hash bits each repetition tries to set to zero (b).
+    /// Between 1 and 32.
+    const ZERO_BITS: usize;
+    /// The number of repetitions (r). At most 256.
+    const REPETITIONS: usize;
+    /// The number of challenges sampled per repetition (2^t). At most 2^16.
+    const SAMPLES: usize;
+    /// The maximum sum of the hash values over all repetitions (S).
+    const SUM: u32;
+
+    /// An upper bound on the base 2 logarithm of the probability that a prover
+    /// making `2^log_queries` random oracle queries outputs a valid proof
+    /// without the online extractor finding a witness.
+    ///
+    /// This is the bound `(Q + 1) (S + 1) binom(S + r - 1, r - 1) 2^{-br}` from
+    /// the proof of online extractability in [1].
+    fn log_extraction_error(log_queries: f64) -> f64 {
+        let (b, r, sum) = (
+            Self::ZERO_BITS as f64,
+            Self::REPETITIONS as f64,
+            Self::SUM as f64,
+        );
+        let log_binom = (1..Self::REPETITIONS)
+            .map(|i| ((sum + i as f64) / i as f64).log2())
+            .sum::<f64>();
+        (2f64.powf(log_queries) + 1.0).log2() + (sum + 1.0).log2() + log_binom
+            - b * r
+    }
+
+    /// The probability that an honest prover's first attempt fails; that is,
+    /// that the minimal hash values of the repetitions sum to more than `SUM`.
+    ///
+    /// [`FischlinTransform`] retries in this case, so this is also the
+    /// probability that proving takes more than one attempt.
+    ///
+    /// [`FischlinTransform`]: struct.FischlinTransform.html
+    fn completeness_error() -> f64 {
+        let range = (1u64 << Self::ZERO_BITS) as f64;
+        let sum = Self::SUM as usize;
+        // The probability that the minimum of `SAMPLES` uniform hash values is
+        // at least `v`.
+        let at_least = |v: usize| {
+            if v as f64 >= range {
+                0.0
+            } else {
+                (Self::SAMPLES as f64 * (-(v as f64) / range).ln_1p()).exp()
+            }
+        };
+        // dist[s] is the probability that the repetitions so far sum to `s`,
+        // for `s <= SUM`.
+        let mut dist = vec![0f64; sum + 1];
+        dist[0] = 1.0;
+        // The probability the sum has already exceeded `SUM`. Accumulated
+        // directly, as it is far too small to compute as `1 - P[sum <= SUM]`.
+        let mut err = 0f64;
+        for _ in 0..Self::REPETITIONS {
+            let mut nxt = vec![0f64; sum + 1];
+            for (s, p) in dist.iter().enumerate() {
+                for (v, q) in nxt[s..].iter_mut().enumerate() {
+                    *q += p * (at_least(v) - at_least(v + 1));
+                }
+                err += p * at_least(sum - s + 1);
+            }
+            dist = nxt;
+        }
+        err
+    }
+}
+
+/// The Fischlin parameters this crate has always used, and the default: the
+/// extraction error is at most `(Q + 1) 2^{-107}` for `Q` random oracle
+/// queries.
+pub struct FischlinDefault;
+
+impl FischlinParameters for FischlinDefault {
+    const ZERO_BITS: usize = 11;
+    const REPETITIONS: usize = 12;
+    const SAMPLES: usize = 0x8000; // 2^15
+    const SUM: u32 = 12;
+}
+
+/// Fischlin parameters for 80-bit security: the extraction error is at most
+/// `(Q + 1) 2^{-80}` for `Q` random oracle queries.
+pub struct Fischlin80;
 
-// Fischlin transform constants. These are the original constants presented in
-// the paper + 2. Achieves 128-bit security.
-const FISHLIN_ZERO_BITS: usize = 11;
-const FISHLIN_REPETITIONS: usize = 12;
-const FISHLIN_SAMPLES: usize = 0x8000; // 2^14
-const FISHLIN_SUM: u32 = 12;
+impl FischlinParameters for Fischlin80 {
+    const ZERO_BITS: usize = 10;
+    const REPETITIONS: usize = 10;
+    const SAMPLES: usize = 0x4000; // 2^14
+    const SUM: u32 = 10;
+}
+
+/// Fischlin parameters for 112-bit security: the extraction error is at most
+/// `(Q + 1) 2^{-112}` for `Q` random oracle queries.
+pub struct Fischlin112;
+
+impl FischlinParameters for Fischlin112 {
+    const ZERO_BITS: usize = 11;
+    const REPETITIONS: usize = 13;
+    const SAMPLES: usize = 0x8000; // 2^15
+    const SUM: u32 = 13;
+}
+
+/// Fischlin parameters for 128-bit security: the extraction error is at most
+/// `(Q + 1) 2^{-128}` for `Q` random oracle queries.
+pub struct Fischlin128;
+
+impl FischlinParameters for Fischlin128 {
+    const ZERO_BITS: usize = 12;
+    const REPETITIONS: usize = 13;
+    const SAMPLES: usize = 0x10000; // 2^16
+    const SUM: u32 = 13;
+}
+
+/// A Fischlin proof: the commitment, sample index and response of each
+/// repetition.
+pub type FischlinProof<T> =
+    Vec<(<T as SigmaProtocol>::T, u16, <T as SigmaProtocol>::R)>;
 
-/// Iterprets as an integer of `FISHLIN_ZERO_BITS` length.
-fn fischlin_bits(bytes: &[u8]) -> u32 {
+/// Iterprets as an integer of `zero_bits` length.
+fn fischlin_bits(bytes: &[u8], zero_bits: usize) -> u32 {
     let mut word = 0;
-    for i in 0..4 {
+    for b in &bytes[..4] {
         word <<= 8;
-        word |= bytes[i] as u32;
+        word |= *b as u32;
     }
-    word >> (32 - FISHLIN_ZERO_BITS)
+    word >> (32 - zero_bits)
 }
 
-impl<T: SigmaProtocol, H: RO + ?Sized> Relation for FischlinTransform<T, H> {
+impl<T: SigmaProtocol, H: RO + ?Sized, P: FischlinParameters> Relation
+    for FischlinTransform<T, H, P>
+{
     type X = T::X;
     type W = T::W;
 
@@ -107,7 +297,8 @@ impl<T: SigmaProtocol, H: RO + ?Sized> Relation for FischlinTransform<T, H> {
     }
 }
 
-impl<T: SigmaProtocol, H: RO + ?Sized> NIZK for FischlinTransform<T, H>
+impl<T: UniqueResponses, H: RO + ?Sized, P: FischlinParameters> NIZK
+    for FischlinTransform<T, H, P>
 where
     Standard: Distribution<T::C>,
     T::X: AsRef<[u8]> + Sync,
@@ -117,32 +308,120 @@ where
     T::R: AsRef<[u8]> + Send,
     (T::T, u16, T::R): Sync,
 {
-    type Proof = Vec<(T::T, u16, T::R)>;
+    type Proof = FischlinProof<T>;
 
-    fn prove<R: Split + Rng + CryptoRng>(
+    fn prove<R: Split + Rng + CryptoRng + Send>(
         x: &Self::X,
         w: &Self::W,
         rng: &mut R,
-    ) -> Self::Proof
-    where
-        R: Send,
-    {
-        let rngs = (0..FISHLIN_REPETITIONS)
-            .map(|_| rng.split())
+    ) -> Self::Proof {
+        loop {
+            if let Some(pi) = Self::try_prove(x, w, rng) {
+                return pi;
+            }
+        }
+    }
+
+    fn verify(x: &Self::X, pi: &Self::Proof) -> bool {
+        if pi.len() != P::REPETITIONS {
+            return false;
+        }
+        // For each repetition
+        let bits = pi
+            .par_iter()
+            .enumerate()
+            .map(|(i, &(ref t, j, ref r))| {
+                // Reconstruct the challenge
+                let c = H::seq_query(
+                    &[
+                        x.as_ref(),
+                        t.as_ref(),
+                        &(i as u8).to_le_bytes()[..],
+                        &j.to_le_bytes()[..],
+                    ][..],
+                )
+                .into_rng()
+                .gen();
+                // Verify sigma protocol
+                if !T::finish_verify(x, t, &c, r) {
+                    None
+                } else {
+                    // And record the result bits
+                    let rnd =
+                        H::seq_query(&[t.as_ref(), c.as_ref(), r.as_ref()][..])
+                            .raw();
+                    Some(fischlin_bits(rnd.as_ref(), P::ZERO_BITS))
+                }
+            })
             .collect::<Vec<_>>();
-        // Repeat FISHLIN_REPETITIONS times, threaded, with an rng for each.
-        rngs.into_par_iter()
+        if bits.iter().any(|b| b.is_none()) {
+            return false;
+        }
+        // Ensure sufficiently small sum
+        bits.iter().fold(0, |n, b| n + b.unwrap()) <= P::SUM
+    }
+}
+
+impl<T: UniqueResponses, H: RO + ?Sized, P: FischlinParameters>
+    FischlinTransform<T, H, P>
+where
+    Standard: Distribution<T::C>,
+    T::X: AsRef<[u8]> + Sync,
+    T::W: AsRef<[u8]> + Sync,
+    T::T: AsRef<[u8]> + Send,
+    T::C: AsRef<[u8]>,
+    T::R: AsRef<[u8]> + Send,
+    (T::T, u16, T::R): Sync,
+{
+    /// Creates a proof as [`NIZK::prove`] does, running in the pool chosen by
+    /// `par`.
+    ///
+    /// [`NIZK::prove`]: trait.NIZK.html#tymethod.prove
+    pub fn prove_in<R: Split + Rng + CryptoRng + Send>(
+        par: Parallelism,
+        x: &T::X,
+        w: &T::W,
+        rng: &mut R,
+    ) -> FischlinProof<T> {
+        par.install(|| <Self as NIZK>::prove(x, w, rng))
+    }
+
+    /// Verifies a proof as [`NIZK::verify`] does, running in the pool chosen by
+    /// `par`.
+    ///
+    /// [`NIZK::verify`]: trait.NIZK.html#tymethod.verify
+    pub fn verify_in(
+        par: Parallelism,
+        x: &T::X,
+        pi: &FischlinProof<T>,
+    ) -> bool {
+        par.install(|| <Self as NIZK>::verify(x, pi))
+    }
+
+    /// Makes a single proving attempt, which fails with probability
+    /// [`P::completeness_error()`].
+    ///
+    /// [`P::completeness_error()`]: trait.FischlinParameters.html#method.completeness_error
+    pub fn try_prove<R: Split + Rng + CryptoRng + Send>(
+        x: &T::X,
+        w: &T::W,
+        rng: &mut R,
+    ) -> Option<FischlinProof<T>> {
+        let rngs = (0..P::REPETITIONS).map(|_| rng.split()).collect::<Vec<_>>();
+        // Repeat P::REPETITIONS times, threaded, with an rng for each.
+        let reps = rngs
+            .into_par_iter()
             .enumerate()
             .map(|(i, mut rng)| {
                 // Start the sigma protocol
                 let (z, t) = T::prove_step_1(x, w, &mut rng);
                 let mut min_idx: usize = 0;
                 let mut min_r: Option<T::R> = None;
-                let mut min_val: u32 = u32::max_value();
+                let mut min_val: u32 = u32::MAX;
                 // Sample challenges until the result has a low enough hash.
-                for j in 0..FISHLIN_SAMPLES {
-                    // Challenge is uniquely determined by stage 1, repetition and sample no. This
-                    // departs from the paper, through RO.
+                for j in 0..P::SAMPLES {
+                    // Challenge is uniquely determined by stage 1, repetition
+                    // and sample no. This departs from the paper, through RO.
                     let c = H::seq_query(
                         &[
                             x.as_ref(),
@@ -158,7 +437,7 @@ where
                     let rnd =
                         H::seq_query(&[t.as_ref(), c.as_ref(), r.as_ref()][..])
                             .raw();
-                    let bits = fischlin_bits(rnd.as_ref());
+                    let bits = fischlin_bits(rnd.as_ref(), P::ZERO_BITS);
                     if bits < min_val || min_r.is_none() {
                         min_r = Some(r);
                         min_val = bits;
@@ -169,60 +448,88 @@ where
                     };
                 }
                 (
-                    t,
-                    min_idx as u16,
-                    min_r.expect("at least one sample is generated"),
+                    min_val,
+                    (
+                        t,
+                        min_idx as u16,
+                        min_r.expect("at least one sample is generated"),
+                    ),
                 )
             })
-            .collect::<Vec<_>>()
-        // FIXME: This has a small chance of failing. (proof not being correct)
-        //  - calculate the chance
-        //  - if non-negligible: increase FISHLIN_SAMPLES, or simply rerun on failure.
+            .collect::<Vec<_>>();
+        // With probability `P::completeness_error()`, the hash values are too
+        // large for the proof to verify.
+        if reps.iter().map(|(v, _)| v).sum::<u32>() > P::SUM {
+            return None;
+        }
+        Some(reps.into_iter().map(|(_, rep)| rep).collect())
     }
+}
 
-    fn verify(x: &Self::X, pi: &Self::Proof) -> bool {
-        if pi.len() != FISHLIN_REPETITIONS {
-            return false;
+/// Encoded as exactly `P::REPETITIONS` triples of commitment, sample index and
+/// response, without a length prefix. For the dual proof of exponent over
+/// BLS12-381's G1, a triple is 162 bytes, so a proof is 1944 bytes with
+/// [`FischlinDefault`] and 2106 bytes with [`Fischlin128`].
+///
+/// [`FischlinDefault`]: struct.FischlinDefault.html
+/// [`Fischlin128`]: struct.Fischlin128.html
+impl<T: UniqueResponses, H: RO + ?Sized, P: FischlinParameters> SerializableNIZK
+    for FischlinTransform<T, H, P>
+where
+    Standard: Distribution<T::C>,
+    T::X: AsRef<[u8]> + Sync,
+    T::W: AsRef<[u8]> + Sync,
+    T::T: AsRef<[u8]> + Send + Serializable,
+    T::C: AsRef<[u8]>,
+    T::R: AsRef<[u8]> + Send + Serializable,
+    (T::T, u16, T::R): Sync,
+{
+    fn write_proof<W: Write>(pi: &Self::Proof, mut out: W) -> io::Result<()> {
+        if pi.len() != P::REPETITIONS {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Fischlin proof has the wrong number of repetitions",
+            ));
         }
-        // For each repetition
-        let bits = pi
-            .par_iter()
-            .enumerate()
-            .map(|(i, &(ref t, j, ref r))| {
-                // Reconstruct the challenge
-                let c = H::seq_query(
-                    &[
-                        &x.as_ref(),
-                        &t.as_ref(),
-                        &(i as u8).to_le_bytes()[..],
-                        &(j as u16).to_le_bytes()[..],
-                    ][..],
-                )
-                .into_rng()
-                .gen();
-                // Verify sigma protocol
-                if !T::finish_verify(x, t, &c, r) {
-                    None
-                } else {
-                    // And record the result bits
-                    let rnd =
-                        H::seq_query(&[t.as_ref(), c.as_ref(), r.as_ref()][..])
-                            .raw();
-                    Some(fischlin_bits(rnd.as_ref()))
-                }
-            })
-            .collect::<Vec<_>>();
-        if bits.iter().any(|b| b.is_none()) {
-            return false;
+        for rep in pi.iter() {
+            rep.write(&mut out)?;
         }
-        // Ensure sufficiently small sum
-        bits.iter().fold(0, |n, b| n + b.unwrap()) <= FISHLIN_SUM
+        Ok(())
     }
+
+    fn read_proof<R: Read>(mut inp: R) -> Result<Self::Proof, ImportError> {
+        (0..P::REPETITIONS)
+            .map(|_| Serializable::read(&mut inp))
+            .collect()
+    }
+}
+
+/// Uses the default, verifying each proof on its own.
+impl<T: UniqueResponses, H: RO + ?Sized, P: FischlinParameters> BatchVerify
+    for FischlinTransform<T, H, P>
+where
+    Standard: Distribution<T::C>,
+    T::X: AsRef<[u8]> + Sync,
+    T::W: AsRef<[u8]> + Sync,
+    T::T: AsRef<[u8]> + Send,
+    T::C: AsRef<[u8]>,
+    T::R: AsRef<[u8]> + Send,
+    (T::T, u16, T::R): Sync,
+{
 }
 
+/// A transcript to verify: the statement, commitment, challenge and response.
+pub type Transcript<'a, T> = (
+    &'a <T as Relation>::X,
+    &'a <T as SigmaProtocol>::T,
+    <T as SigmaProtocol>::C,
+    &'a <T as SigmaProtocol>::R,
+);
+
 /// A Sigma protocol
 pub trait SigmaProtocol: Relation {
-    /// Auxiliary prover information passed from the first to the second proving step.
+    /// Auxiliary prover information passed from the first to the second proving
+    /// step.
     type Z; //: Copy;
     /// The initial commitment
     type T; //: Copy;
@@ -251,6 +558,60 @@ pub trait SigmaProtocol: Relation {
         c: &Self::C,
         r: &Self::R,
     ) -> bool;
+    /// Final verification of many transcripts, returning whether all are
+    /// accepted.
+    ///
+    /// The default checks each in turn. Protocols whose checks are equations in
+    /// a group may fold them into one, using `rng` for the coefficients.
+    fn batch_finish_verify<R: Rng + CryptoRng + ?Sized>(
+        transcripts: &[Transcript<Self>],
+        _: &mut R,
+    ) -> bool {
+        transcripts
+            .iter()
+            .all(|(x, t, c, r)| Self::finish_verify(x, t, c, r))
+    }
+}
+
+/// A sigma protocol with a special honest-verifier zero-knowledge simulator.
+pub trait SimulatableSigmaProtocol: SigmaProtocol {
+    /// Creates a commitment and response accepted for the challenge `c`,
+    /// without a witness.
+    ///
+    /// These are distributed as in an honest run of the protocol with challenge
+    /// `c`.
+    fn simulate<R: Rng + CryptoRng + ?Sized>(
+        x: &Self::X,
+        c: &Self::C,
+        rng: &mut R,
+    ) -> (Self::T, Self::R);
+}
+
+/// A sigma protocol with computationally unique responses: for a statement,
+/// commitment and challenge, finding two different responses that both verify
+/// is infeasible.
+///
+/// Fischlin's transform relies on this, and so [`FischlinTransform`] requires
+/// it. Responses of an [`OrComposition`] are not unique, as the prover may
+/// split the challenge between the branches in many ways, and Kondi and shelat
+/// [1] show that Fischlin's transform of an OR proof need not be a proof of
+/// knowledge. Such protocols are for the [`FiatShamirTransform`] only.
+///
+/// [1] Yashvanth Kondi and abhi shelat. Improved straight-line extraction in
+/// the random oracle model with applications to signature aggregation.
+/// ASIACRYPT 2022.
+///
+/// [`FischlinTransform`]: struct.FischlinTransform.html
+/// [`FiatShamirTransform`]: struct.FiatShamirTransform.html
+/// [`OrComposition`]: ../compose/struct.OrComposition.html
+pub trait UniqueResponses: SigmaProtocol {}
+
+/// Challenges forming a group, so that a challenge can be split into shares.
+pub trait ChallengeGroup: Sized {
+    /// Combines two shares.
+    fn add(&self, other: &Self) -> Self;
+    /// Removes a share.
+    fn sub(&self, other: &Self) -> Self;
 }
 
 /// The Fiat-Shamir transform of a sigma protocol.
@@ -287,17 +648,70 @@ where
         (t, r)
     }
 
-    fn verify(x: &Self::X, &(ref t, ref r): &Self::Proof) -> bool {
+    fn verify(x: &Self::X, (t, r): &Self::Proof) -> bool {
         let c = H::seq_query(&[x.as_ref(), t.as_ref()][..]).into_rng().gen();
         T::finish_verify(x, t, &c, r)
     }
 }
 
-/// A sigma protocol proving knowledge of pairs of exponents used to construct pairs of group
-/// elements.
+/// Encoded as the commitment followed by the response.
+impl<T: SigmaProtocol, H: RO + ?Sized> SerializableNIZK
+    for FiatShamirTransform<T, H>
+where
+    Standard: Distribution<T::C>,
+    T::X: AsRef<[u8]>,
+    T::T: AsRef<[u8]> + Serializable,
+    T::R: Serializable,
+{
+    fn write_proof<W: Write>(
+        (t, r): &Self::Proof,
+        mut out: W,
+    ) -> io::Result<()> {
+        t.write(&mut out)?;
+        r.write(&mut out)
+    }
+
+    fn read_proof<R: Read>(mut inp: R) -> Result<Self::Proof, ImportError> {
+        Ok((T::T::read(&mut inp)?, T::R::read(&mut inp)?))
+    }
+}
+
+/// Recomputes each proof's challenge, and checks all transcripts with
+/// [`SigmaProtocol::batch_finish_verify`].
+///
+/// [`SigmaProtocol::batch_finish_verify`]: trait.SigmaProtocol.html#method.batch_finish_verify
+impl<T: SigmaProtocol, H: RO + ?Sized> BatchVerify for FiatShamirTransform<T, H>
+where
+    Standard: Distribution<T::C>,
+    T::X: AsRef<[u8]>,
+    T::T: AsRef<[u8]>,
+{
+    fn batch_verify<R: Rng + CryptoRng + ?Sized>(
+        instances: &[(Self::X, &Self::Proof)],
+        rng: &mut R,
+    ) -> bool
+    where
+        Self::X: Sync,
+        Self::Proof: Sync,
+    {
+        let transcripts = instances
+            .iter()
+            .map(|(x, (t, r))| {
+                let c = H::seq_query(&[x.as_ref(), t.as_ref()][..])
+                    .into_rng()
+                    .gen();
+                (x, t, c, r)
+            })
+            .collect::<Vec<_>>();
+        T::batch_finish_verify(&transcripts, rng)
+    }
+}
+
+/// A sigma protocol proving knowledge of pairs of exponents used to construct
+/// pairs of group elements.
 pub struct DualProofOfExponentSigmaProtocol<C: CurveAffine>(PhantomData<C>);
 
-#[derive(PartialEq, Eq)]
+#[derive(Clone, PartialEq, Eq)]
 /// A pair of curve elements
 pub struct CurvePair<C: CurveAffine>(pub C, pub C, Vec<u8>);
 
@@ -310,6 +724,7 @@ impl<C: CurveAffine> CurvePair<C> {
     }
 }
 
+#[derive(Clone)]
 /// A pair of field elements
 pub struct FieldPair<F: PrimeField>(pub F, pub F, Vec<u8>);
 
@@ -337,6 +752,31 @@ impl<F: PrimeField> AsRef<[u8]> for FieldPair<F> {
     }
 }
 
+impl<C: CurveAffine> Serializable for CurvePair<C> {
+    fn write<W: Write>(&self, mut out: W) -> io::Result<()> {
+        write_point(&self.0, &mut out)?;
+        write_point(&self.1, &mut out)
+    }
+
+    fn read<R: Read>(mut inp: R) -> Result<Self, ImportError> {
+        Ok(CurvePair::new(read_point(&mut inp)?, read_point(&mut inp)?))
+    }
+}
+
+impl<F: PrimeField> Serializable for FieldPair<F> {
+    fn write<W: Write>(&self, mut out: W) -> io::Result<()> {
+        write_scalar(&self.0, &mut out)?;
+        write_scalar(&self.1, &mut out)
+    }
+
+    fn read<R: Read>(mut inp: R) -> Result<Self, ImportError> {
+        Ok(FieldPair::new(
+            read_scalar(&mut inp)?,
+            read_scalar(&mut inp)?,
+        ))
+    }
+}
+
 impl<F: PrimeField> Distribution<FieldPair<F>> for Standard {
     fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> FieldPair<F> {
         FieldPair::new(F::random(rng), F::random(rng))
@@ -399,4 +839,714 @@ impl<C: CurveAffine> SigmaProtocol for DualProofOfExponentSigmaProtocol<C> {
         u_prime.add_assign(&b.mul(d));
         t_prime.into_affine() == t && u_prime.into_affine() == u
     }
+
+    fn batch_finish_verify<R: Rng + CryptoRng + ?Sized>(
+        transcripts: &[Transcript<Self>],
+        rng: &mut R,
+    ) -> bool {
+        // With random coefficients \rho_i, check in one multiexp that \sum_i
+        // \rho_i (r_i g + c_i a_i - t_i) = 0, over both halves of every
+        // transcript.
+        let rho =
+            random_small_scalars::<C::Scalar, _>(2 * transcripts.len(), rng);
+        let mut g_exp = C::Scalar::zero();
+        let mut pairs = Vec::with_capacity(4 * transcripts.len() + 1);
+        for ((x, t, c, r), rho) in transcripts.iter().zip(rho.chunks(2)) {
+            for (a, t, c, r, rho) in
+                [(x.0, t.0, c.0, r.0, rho[0]), (x.1, t.1, c.1, r.1, rho[1])]
+                    .iter()
+            {
+                let mut r = *r;
+                r.mul_assign(rho);
+                g_exp.add_assign(&r);
+                let mut c = *c;
+                c.mul_assign(rho);
+                pairs.push((*a, c));
+                // Negating the point keeps the coefficient small.
+                let mut t = *t;
+                t.negate();
+                pairs.push((t, *rho));
+            }
+        }
+        pairs.push((C::one(), g_exp));
+        multiexp_pairs(pairs).is_zero()
+    }
+}
+
+/// The responses are determined by the exponents of the commitment.
+impl<C: CurveAffine> UniqueResponses for DualProofOfExponentSigmaProtocol<C> {}
+
+impl<C: CurveAffine> SimulatableSigmaProtocol
+    for DualProofOfExponentSigmaProtocol<C>
+{
+    fn simulate<R: Rng + CryptoRng + ?Sized>(
+        &CurvePair(a, b, _): &Self::X,
+        &FieldPair(c, d, _): &Self::C,
+        rng: &mut R,
+    ) -> (Self::T, Self::R) {
+        let (r, s) = (C::Scalar::random(rng), C::Scalar::random(rng));
+        let mut t = C::one().mul(r);
+        t.add_assign(&a.mul(c));
+        let mut u = C::one().mul(s);
+        u.add_assign(&b.mul(d));
+        (
+            CurvePair::new(t.into_affine(), u.into_affine()),
+            FieldPair::new(r, s),
+        )
+    }
+}
+
+impl<F: PrimeField> ChallengeGroup for FieldPair<F> {
+    fn add(&self, other: &Self) -> Self {
+        let (mut a, mut b) = (self.0, self.1);
+        a.add_assign(&other.0);
+        b.add_assign(&other.1);
+        FieldPair::new(a, b)
+    }
+
+    fn sub(&self, other: &Self) -> Self {
+        let (mut a, mut b) = (self.0, self.1);
+        a.sub_assign(&other.0);
+        b.sub_assign(&other.1);
+        FieldPair::new(a, b)
+    }
+}
+
+/// A sigma protocol proving knowledge of the exponent used to construct a group
+/// element, as in Schnorr's identification scheme.
+pub struct SchnorrSigmaProtocol<C: CurveAffine>(PhantomData<C>);
+
+#[derive(Clone, PartialEq, Eq)]
+/// A single curve element
+pub struct CurvePoint<C: CurveAffine>(pub C, Vec<u8>);
+
+impl<C: CurveAffine> CurvePoint<C> {
+    pub fn new(a: C) -> Self {
+        CurvePoint(a, a.into_uncompressed().as_ref().to_vec())
+    }
+}
+
+#[derive(Clone)]
+/// A single field element
+pub struct FieldElement<F: PrimeField>(pub F, Vec<u8>);
+
+impl<F: PrimeField> FieldElement<F> {
+    pub fn new(a: F) -> Self {
+        let mut vec = Vec::new();
+        for word in a.into_repr().as_ref() {
+            vec.extend(word.to_le_bytes().iter())
+        }
+        FieldElement(a, vec)
+    }
+}
+
+impl<C: CurveAffine> AsRef<[u8]> for CurvePoint<C> {
+    fn as_ref(&self) -> &[u8] {
+        self.1.as_ref()
+    }
+}
+
+impl<F: PrimeField> AsRef<[u8]> for FieldElement<F> {
+    fn as_ref(&self) -> &[u8] {
+        self.1.as_ref()
+    }
+}
+
+impl<C: CurveAffine> Serializable for CurvePoint<C> {
+    fn write<W: Write>(&self, out: W) -> io::Result<()> {
+        write_point(&self.0, out)
+    }
+
+    fn read<R: Read>(inp: R) -> Result<Self, ImportError> {
+        Ok(CurvePoint::new(read_point(inp)?))
+    }
+}
+
+impl<F: PrimeField> Serializable for FieldElement<F> {
+    fn write<W: Write>(&self, out: W) -> io::Result<()> {
+        write_scalar(&self.0, out)
+    }
+
+    fn read<R: Read>(inp: R) -> Result<Self, ImportError> {
+        Ok(FieldElement::new(read_scalar(inp)?))
+    }
+}
+
+impl<F: PrimeField> Distribution<FieldElement<F>> for Standard {
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> FieldElement<F> {
+        FieldElement::new(F::random(rng))
+    }
+}
+
+impl<C: CurveAffine> Relation for SchnorrSigmaProtocol<C> {
+    type X = CurvePoint<C>;
+    type W = FieldElement<C::Scalar>;
+
+    fn check(x: &Self::X, w: &Self::W) -> bool {
+        &CurvePoint::new(C::one().mul(w.0).into_affine()) == x
+    }
+}
+
+impl<C: CurveAffine> SigmaProtocol for SchnorrSigmaProtocol<C> {
+    type Z = FieldElement<C::Scalar>;
+    type T = CurvePoint<C>;
+    type C = FieldElement<C::Scalar>;
+    type R = FieldElement<C::Scalar>;
+
+    fn prove_step_1<R: Rng + CryptoRng + ?Sized>(
+        _: &Self::X,
+        _: &Self::W,
+        rng: &mut R,
+    ) -> (Self::Z, Self::T) {
+        let z = FieldElement::new(C::Scalar::random(rng));
+        let t = CurvePoint::new(C::one().mul(z.0).into_affine());
+        (z, t)
+    }
+
+    fn prove_step_2(
+        _: &Self::X,
+        &FieldElement(a, _): &Self::W,
+        &FieldElement(mut v, _): &Self::Z,
+        &FieldElement(mut c, _): &Self::C,
+    ) -> Self::R {
+        c.mul_assign(&a);
+        v.sub_assign(&c);
+        FieldElement::new(v)
+    }
+
+    fn finish_verify(
+        &CurvePoint(a, _): &Self::X,
+        &CurvePoint(t, _): &Self::T,
+        &FieldElement(c, _): &Self::C,
+        &FieldElement(r, _): &Self::R,
+    ) -> bool {
+        let mut t_prime = C::one().mul(r);
+        t_prime.add_assign(&a.mul(c));
+        t_prime.into_affine() == t
+    }
+}
+
+/// The response is determined by the commitment's exponent.
+impl<C: CurveAffine> UniqueResponses for SchnorrSigmaProtocol<C> {}
+
+impl<C: CurveAffine> SimulatableSigmaProtocol for SchnorrSigmaProtocol<C> {
+    fn simulate<R: Rng + CryptoRng + ?Sized>(
+        &CurvePoint(a, _): &Self::X,
+        &FieldElement(c, _): &Self::C,
+        rng: &mut R,
+    ) -> (Self::T, Self::R) {
+        let r = C::Scalar::random(rng);
+        let mut t = C::one().mul(r);
+        t.add_assign(&a.mul(c));
+        (CurvePoint::new(t.into_affine()), FieldElement::new(r))
+    }
+}
+
+impl<F: PrimeField> ChallengeGroup for FieldElement<F> {
+    fn add(&self, other: &Self) -> Self {
+        let mut a = self.0;
+        a.add_assign(&other.0);
+        FieldElement::new(a)
+    }
+
+    fn sub(&self, other: &Self) -> Self {
+        let mut a = self.0;
+        a.sub_assign(&other.0);
+        FieldElement::new(a)
+    }
+}
+
+/// A sigma protocol proving that two group elements share an exponent with
+/// respect to two bases, due to Chaum and Pedersen [1].
+///
+/// [1] David Chaum and Torben Pryds Pedersen. Wallet databases with observers.
+/// CRYPTO 1992.
+pub struct DLEQSigmaProtocol<C: CurveAffine>(PhantomData<C>);
+
+#[derive(Clone, PartialEq, Eq)]
+/// A statement `(g, u, g^a, u^a)` of a shared exponent `a`
+pub struct DLEQStatement<C: CurveAffine> {
+    /// The first base
+    pub g: C,
+    /// The second base
+    pub u: C,
+    /// `g^a`
+    pub ga: C,
+    /// `u^a`
+    pub ua: C,
+    bytes: Vec<u8>,
+}
+
+impl<C: CurveAffine> DLEQStatement<C> {
+    pub fn new(g: C, u: C, ga: C, ua: C) -> Self {
+        let mut bytes = Vec::new();
+        for p in [g, u, ga, ua].iter() {
+            bytes.extend(p.into_uncompressed().as_ref().iter().cloned());
+        }
+        DLEQStatement {
+            g,
+            u,
+            ga,
+            ua,
+            bytes,
+        }
+    }
+}
+
+impl<C: CurveAffine> AsRef<[u8]> for DLEQStatement<C> {
+    fn as_ref(&self) -> &[u8] {
+        self.bytes.as_ref()
+    }
+}
+
+impl<C: CurveAffine> Relation for DLEQSigmaProtocol<C> {
+    type X = DLEQStatement<C>;
+    type W = FieldElement<C::Scalar>;
+
+    fn check(x: &Self::X, w: &Self::W) -> bool {
+        x.g.mul(w.0).into_affine() == x.ga && x.u.mul(w.0).into_affine() == x.ua
+    }
+}
+
+impl<C: CurveAffine> SigmaProtocol for DLEQSigmaProtocol<C> {
+    type Z = FieldElement<C::Scalar>;
+    type T = CurvePair<C>;
+    type C = FieldElement<C::Scalar>;
+    type R = FieldElement<C::Scalar>;
+
+    fn prove_step_1<R: Rng + CryptoRng + ?Sized>(
+        x: &Self::X,
+        _: &Self::W,
+        rng: &mut R,
+    ) -> (Self::Z, Self::T) {
+        let z = FieldElement::new(C::Scalar::random(rng));
+        let t = CurvePair::new(
+            x.g.mul(z.0).into_affine(),
+            x.u.mul(z.0).into_affine(),
+        );
+        (z, t)
+    }
+
+    fn prove_step_2(
+        _: &Self::X,
+        &FieldElement(a, _): &Self::W,
+        &FieldElement(mut v, _): &Self::Z,
+        &FieldElement(mut c, _): &Self::C,
+    ) -> Self::R {
+        c.mul_assign(&a);
+        v.sub_assign(&c);
+        FieldElement::new(v)
+    }
+
+    fn finish_verify(
+        x: &Self::X,
+        &CurvePair(t, u, _): &Self::T,
+        &FieldElement(c, _): &Self::C,
+        &FieldElement(r, _): &Self::R,
+    ) -> bool {
+        let mut t_prime = x.g.mul(r);
+        t_prime.add_assign(&x.ga.mul(c));
+        let mut u_prime = x.u.mul(r);
+        u_prime.add_assign(&x.ua.mul(c));
+        t_prime.into_affine() == t && u_prime.into_affine() == u
+    }
+}
+
+/// The response is determined by the exponent of the commitment's first
+/// element.
+impl<C: CurveAffine> UniqueResponses for DLEQSigmaProtocol<C> {}
+
+impl<C: CurveAffine> SimulatableSigmaProtocol for DLEQSigmaProtocol<C> {
+    fn simulate<R: Rng + CryptoRng + ?Sized>(
+        x: &Self::X,
+        &FieldElement(c, _): &Self::C,
+        rng: &mut R,
+    ) -> (Self::T, Self::R) {
+        let r = C::Scalar::random(rng);
+        let mut t = x.g.mul(r);
+        t.add_assign(&x.ga.mul(c));
+        let mut u = x.u.mul(r);
+        u.add_assign(&x.ua.mul(c));
+        (
+            CurvePair::new(t.into_affine(), u.into_affine()),
+            FieldElement::new(r),
+        )
+    }
+}
+
+/// A sigma protocol proving that elements of two different groups share an
+/// exponent with respect to a base in each, such as `g^a` in G1 and `h^a` in
+/// G2.
+///
+/// This is [`DLEQSigmaProtocol`] across groups of the same order. Where a
+/// pairing is available, the same relation can be checked as
+/// `e(g^a, h) = e(g, h^a)`, but only for known exponents.
+///
+/// [`DLEQSigmaProtocol`]: struct.DLEQSigmaProtocol.html
+pub struct CrossGroupSigmaProtocol<
+    G1: CurveAffine,
+    G2: CurveAffine<Scalar = G1::Scalar>,
+>(PhantomData<(G1, G2)>);
+
+#[derive(Clone, PartialEq, Eq)]
+/// An element of each of two curves
+pub struct CrossGroupPair<G1: CurveAffine, G2: CurveAffine>(
+    pub G1,
+    pub G2,
+    Vec<u8>,
+);
+
+impl<G1: CurveAffine, G2: CurveAffine> CrossGroupPair<G1, G2> {
+    pub fn new(a: G1, b: G2) -> Self {
+        let mut vec = Vec::new();
+        vec.extend(a.into_uncompressed().as_ref().iter().cloned());
+        vec.extend(b.into_uncompressed().as_ref().iter().cloned());
+        CrossGroupPair(a, b, vec)
+    }
+}
+
+impl<G1: CurveAffine, G2: CurveAffine> AsRef<[u8]> for CrossGroupPair<G1, G2> {
+    fn as_ref(&self) -> &[u8] {
+        self.2.as_ref()
+    }
+}
+
+impl<G1: CurveAffine, G2: CurveAffine> Serializable for CrossGroupPair<G1, G2> {
+    fn write<W: Write>(&self, mut out: W) -> io::Result<()> {
+        write_point(&self.0, &mut out)?;
+        write_point(&self.1, &mut out)
+    }
+
+    fn read<R: Read>(mut inp: R) -> Result<Self, ImportError> {
+        Ok(CrossGroupPair::new(
+            read_point(&mut inp)?,
+            read_point(&mut inp)?,
+        ))
+    }
+}
+
+#[derive(Clone, PartialEq, Eq)]
+/// A statement `(g, h, g^a, h^a)` of an exponent `a` shared across groups
+pub struct CrossGroupStatement<G1: CurveAffine, G2: CurveAffine> {
+    /// The base in the first group
+    pub g: G1,
+    /// The base in the second group
+    pub h: G2,
+    /// `g^a`
+    pub ga: G1,
+    /// `h^a`
+    pub ha: G2,
+    bytes: Vec<u8>,
+}
+
+impl<G1: CurveAffine, G2: CurveAffine> CrossGroupStatement<G1, G2> {
+    pub fn new(g: G1, h: G2, ga: G1, ha: G2) -> Self {
+        let mut bytes = Vec::new();
+        for p in [g, ga].iter() {
+            bytes.extend(p.into_uncompressed().as_ref().iter().cloned());
+        }
+        for p in [h, ha].iter() {
+            bytes.extend(p.into_uncompressed().as_ref().iter().cloned());
+        }
+        CrossGroupStatement {
+            g,
+            h,
+            ga,
+            ha,
+            bytes,
+        }
+    }
+}
+
+impl<G1: CurveAffine, G2: CurveAffine> AsRef<[u8]>
+    for CrossGroupStatement<G1, G2>
+{
+    fn as_ref(&self) -> &[u8] {
+        self.bytes.as_ref()
+    }
+}
+
+impl<G1: CurveAffine, G2: CurveAffine<Scalar = G1::Scalar>> Relation
+    for CrossGroupSigmaProtocol<G1, G2>
+{
+    type X = CrossGroupStatement<G1, G2>;
+    type W = FieldElement<G1::Scalar>;
+
+    fn check(x: &Self::X, w: &Self::W) -> bool {
+        x.g.mul(w.0).into_affine() == x.ga && x.h.mul(w.0).into_affine() == x.ha
+    }
+}
+
+impl<G1: CurveAffine, G2: CurveAffine<Scalar = G1::Scalar>> SigmaProtocol
+    for CrossGroupSigmaProtocol<G1, G2>
+{
+    type Z = FieldElement<G1::Scalar>;
+    type T = CrossGroupPair<G1, G2>;
+    type C = FieldElement<G1::Scalar>;
+    type R = FieldElement<G1::Scalar>;
+
+    fn prove_step_1<R: Rng + CryptoRng + ?Sized>(
+        x: &Self::X,
+        _: &Self::W,
+        rng: &mut R,
+    ) -> (Self::Z, Self::T) {
+        let z = FieldElement::new(G1::Scalar::random(rng));
+        let t = CrossGroupPair::new(
+            x.g.mul(z.0).into_affine(),
+            x.h.mul(z.0).into_affine(),
+        );
+        (z, t)
+    }
+
+    fn prove_step_2(
+        _: &Self::X,
+        &FieldElement(a, _): &Self::W,
+        &FieldElement(mut v, _): &Self::Z,
+        &FieldElement(mut c, _): &Self::C,
+    ) -> Self::R {
+        c.mul_assign(&a);
+        v.sub_assign(&c);
+        FieldElement::new(v)
+    }
+
+    fn finish_verify(
+        x: &Self::X,
+        &CrossGroupPair(t, u, _): &Self::T,
+        &FieldElement(c, _): &Self::C,
+        &FieldElement(r, _): &Self::R,
+    ) -> bool {
+        let mut t_prime = x.g.mul(r);
+        t_prime.add_assign(&x.ga.mul(c));
+        let mut u_prime = x.h.mul(r);
+        u_prime.add_assign(&x.ha.mul(c));
+        t_prime.into_affine() == t && u_prime.into_affine() == u
+    }
+}
+
+/// The response is determined by the exponent of the commitment's first
+/// element.
+impl<G1: CurveAffine, G2: CurveAffine<Scalar = G1::Scalar>> UniqueResponses
+    for CrossGroupSigmaProtocol<G1, G2>
+{
+}
+
+impl<G1: CurveAffine, G2: CurveAffine<Scalar = G1::Scalar>>
+    SimulatableSigmaProtocol for CrossGroupSigmaProtocol<G1, G2>
+{
+    fn simulate<R: Rng + CryptoRng + ?Sized>(
+        x: &Self::X,
+        &FieldElement(c, _): &Self::C,
+        rng: &mut R,
+    ) -> (Self::T, Self::R) {
+        let r = G1::Scalar::random(rng);
+        let mut t = x.g.mul(r);
+        t.add_assign(&x.ga.mul(c));
+        let mut u = x.h.mul(r);
+        u.add_assign(&x.ha.mul(c));
+        (
+            CrossGroupPair::new(t.into_affine(), u.into_affine()),
+            FieldElement::new(r),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pairing::bls12_381::{Fr, G1Affine, G1};
+    use sha3::Sha3_256;
+
+    type Schnorr = SchnorrSigmaProtocol<G1Affine>;
+
+    fn rng() -> impl Rng + CryptoRng + Split + Send {
+        Sha3_256::query(b"poe test").into_rng()
+    }
+
+    /// Proves `x` with `N`, checks the proof verifies, also after an encoding
+    /// round trip, and is rejected for `other`.
+    fn prove_and_verify<N: SerializableNIZK>(
+        x: &N::X,
+        w: &N::W,
+        other: &N::X,
+    ) -> N::Proof {
+        assert!(N::check(x, w));
+        let pi = N::prove(x, w, &mut rng());
+        assert!(N::verify(x, &pi));
+        assert!(!N::verify(other, &pi));
+        let read = N::read_proof(&N::proof_bytes(&pi)[..]).unwrap();
+        assert!(N::verify(x, &read));
+        pi
+    }
+
+    /// Checks that simulated transcripts verify, and that changing the response
+    /// breaks them.
+    fn check_simulation<T: SimulatableSigmaProtocol>(x: &T::X)
+    where
+        Standard: Distribution<T::C> + Distribution<T::R>,
+    {
+        let mut rng = rng();
+        let c = rng.gen();
+        let (t, r) = T::simulate(x, &c, &mut rng);
+        assert!(T::finish_verify(x, &t, &c, &r));
+        assert!(!T::finish_verify(x, &t, &c, &rng.gen()));
+    }
+
+    fn schnorr_instance() -> (CurvePoint<G1Affine>, FieldElement<Fr>) {
+        let w = FieldElement::new(Fr::random(&mut rng()));
+        (CurvePoint::new(G1Affine::one().mul(w.0).into_affine()), w)
+    }
+
+    #[test]
+    fn schnorr_proves_and_rejects() {
+        let (x, w) = schnorr_instance();
+        let other = CurvePoint::new(G1::random(&mut rng()).into_affine());
+        assert!(!Schnorr::check(&other, &w));
+        let (t, _) = prove_and_verify::<FiatShamirTransform<Schnorr, Sha3_256>>(
+            &x, &w, &other,
+        );
+        let forged = (t, FieldElement::new(Fr::random(&mut rng())));
+        assert!(!FiatShamirTransform::<Schnorr, Sha3_256>::verify(
+            &x, &forged
+        ));
+        let mut pi = prove_and_verify::<FischlinTransform<Schnorr, Sha3_256>>(
+            &x, &w, &other,
+        );
+        pi[0].2 = FieldElement::new(Fr::random(&mut rng()));
+        assert!(!FischlinTransform::<Schnorr, Sha3_256>::verify(&x, &pi));
+        check_simulation::<Schnorr>(&x);
+    }
+
+    #[test]
+    fn dleq_proves_and_rejects() {
+        type Dleq = DLEQSigmaProtocol<G1Affine>;
+        let mut rng = rng();
+        let (g, u) = (G1::random(&mut rng), G1::random(&mut rng));
+        let (g, u) = (g.into_affine(), u.into_affine());
+        let a = Fr::random(&mut rng);
+        let x = DLEQStatement::new(
+            g,
+            u,
+            g.mul(a).into_affine(),
+            u.mul(a).into_affine(),
+        );
+        let w = FieldElement::new(a);
+        // Different exponents for the two bases.
+        let mut b = a;
+        b.add_assign(&Fr::one());
+        let other = DLEQStatement::new(g, u, x.ga, u.mul(b).into_affine());
+        assert!(!Dleq::check(&other, &w));
+        let (t, _) = prove_and_verify::<FiatShamirTransform<Dleq, Sha3_256>>(
+            &x, &w, &other,
+        );
+        let forged = (t, FieldElement::new(Fr::random(&mut rng)));
+        assert!(!FiatShamirTransform::<Dleq, Sha3_256>::verify(&x, &forged));
+        let mut pi = prove_and_verify::<FischlinTransform<Dleq, Sha3_256>>(
+            &x, &w, &other,
+        );
+        pi[0].2 = FieldElement::new(Fr::random(&mut rng));
+        assert!(!FischlinTransform::<Dleq, Sha3_256>::verify(&x, &pi));
+        check_simulation::<Dleq>(&x);
+    }
+
+    #[test]
+    fn dual_batch_rejects_one_bad_transcript() {
+        type Dual = DualProofOfExponentSigmaProtocol<G1Affine>;
+        let mut rng = rng();
+        let instances = (0..4)
+            .map(|_| {
+                let w: FieldPair<Fr> = rng.gen();
+                let x = CurvePair::new(
+                    G1Affine::one().mul(w.0).into_affine(),
+                    G1Affine::one().mul(w.1).into_affine(),
+                );
+                let (z, t) = Dual::prove_step_1(&x, &w, &mut rng);
+                let c: FieldPair<Fr> = rng.gen();
+                let r = Dual::prove_step_2(&x, &w, &z, &c);
+                (x, t, c, r)
+            })
+            .collect::<Vec<_>>();
+        let mut transcripts = instances
+            .iter()
+            .map(|(x, t, c, r)| (x, t, c.clone(), r))
+            .collect::<Vec<_>>();
+        assert!(Dual::batch_finish_verify(&transcripts, &mut rng));
+        let bad = rng.gen();
+        let (x, t, c, _) = &instances[2];
+        assert!(!Dual::finish_verify(x, t, c, &bad));
+        transcripts[2].3 = &bad;
+        assert!(!Dual::batch_finish_verify(&transcripts, &mut rng));
+    }
+
+    #[test]
+    fn fischlin_presets_meet_their_security_level() {
+        // With a single query, the `(Q + 1)` factor contributes one bit.
+        assert!(FischlinDefault::log_extraction_error(0.0) <= 1.0 - 107.0);
+        assert!(Fischlin80::log_extraction_error(0.0) <= 1.0 - 80.0);
+        assert!(Fischlin112::log_extraction_error(0.0) <= 1.0 - 112.0);
+        assert!(Fischlin128::log_extraction_error(0.0) <= 1.0 - 128.0);
+    }
+
+    #[test]
+    fn fischlin_default_keeps_original_parameters() {
+        assert_eq!(FischlinDefault::ZERO_BITS, 11);
+        assert_eq!(FischlinDefault::REPETITIONS, 12);
+        assert_eq!(FischlinDefault::SAMPLES, 0x8000);
+        assert_eq!(FischlinDefault::SUM, 12);
+    }
+
+    /// One-bit hashes, two repetitions, and `SAMPLES` samples each, so the
+    /// completeness error can be worked out by hand.
+    struct OneBit<const SAMPLES: usize, const SUM: u32>;
+
+    impl<const SAMPLES: usize, const SUM: u32> FischlinParameters
+        for OneBit<SAMPLES, SUM>
+    {
+        const ZERO_BITS: usize = 1;
+        const REPETITIONS: usize = 2;
+        const SAMPLES: usize = SAMPLES;
+        const SUM: u32 = SUM;
+    }
+
+    /// Parameters small enough that proving attempts often fail.
+    struct Tiny;
+
+    impl FischlinParameters for Tiny {
+        const ZERO_BITS: usize = 4;
+        const REPETITIONS: usize = 4;
+        const SAMPLES: usize = 2;
+        const SUM: u32 = 16;
+    }
+
+    #[test]
+    fn completeness_error_matches_hand_computation() {
+        // Each hash is 1 with probability 1/2; any 1 exceeds a sum of 0.
+        assert!((OneBit::<1, 0>::completeness_error() - 0.75).abs() < 1e-12);
+        // The minimum of two samples is 1 with probability 1/4; both
+        // repetitions must be 1 to exceed a sum of 1.
+        assert!((OneBit::<2, 1>::completeness_error() - 0.0625).abs() < 1e-12);
+        assert_eq!(OneBit::<1, 2>::completeness_error(), 0.0);
+    }
+
+    #[test]
+    fn try_prove_fails_at_the_predicted_rate() {
+        type Tight = FischlinTransform<Schnorr, Sha3_256, Tiny>;
+        let expected = Tiny::completeness_error();
+        assert!(0.2 < expected && expected < 0.8);
+        let (x, w) = schnorr_instance();
+        let mut rng = rng();
+        let trials = 400;
+        let mut failures = 0;
+        for _ in 0..trials {
+            match Tight::try_prove(&x, &w, &mut rng) {
+                Some(pi) => assert!(Tight::verify(&x, &pi)),
+                None => failures += 1,
+            }
+        }
+        // The standard deviation of the observed rate is below 0.025.
+        let observed = failures as f64 / trials as f64;
+        assert!((observed - expected).abs() < 0.1);
+        for _ in 0..20 {
+            assert!(Tight::verify(&x, &Tight::prove(&x, &w, &mut rng)));
+        }
+    }
 }
@@ -11,7 +11,10 @@ impl<H: RO + ?Sized> Split for BlockRng<ROOutput<H>> {
         let mut seed = H::RawOutput::default();
         for (i, w) in res.as_ref().iter().enumerate() {
             for j in 0..4 {
-                seed.as_mut()[4 * i + j] = ((*w >> (32 - 8 * j)) & 0xff) as u8;
+                // A shift of 32 wraps to 0, as release builds have always done;
+                // keeping that keeps split streams stable.
+                seed.as_mut()[4 * i + j] =
+                    (w.wrapping_shr(32 - 8 * j as u32) & 0xff) as u8;
             }
         }
         BlockRng::new(ROOutput {
@@ -24,8 +27,8 @@ impl<H: RO + ?Sized> Split for BlockRng<ROOutput<H>> {
 
 /// A random oracle's output
 ///
-/// This implements a block random number generator, by feeding back into the original random
-/// oracle.
+/// This implements a block random number generator, by feeding back into the
+/// original random oracle.
 pub struct ROOutput<H: RO + ?Sized> {
     raw: H::RawOutput,
     ctr: usize,
@@ -36,7 +39,7 @@ impl<H: RO + ?Sized> ROOutput<H> {
     /// Create a new output given raw output bits.
     pub fn new(raw: H::RawOutput) -> Self {
         ROOutput {
-            raw: raw,
+            raw,
             ctr: 0,
             phantom: PhantomData,
         }
@@ -47,7 +50,8 @@ impl<H: RO + ?Sized> ROOutput<H> {
         self.raw
     }
 
-    /// Converts this output into a full RNG using domain-separation on the original random oracle.
+    /// Converts this output into a full RNG using domain-separation on the
+    /// original random oracle.
     pub fn into_rng(self) -> BlockRng<Self> {
         BlockRng::new(self)
     }
@@ -68,8 +72,8 @@ impl<H: RO + ?Sized> BlockRngCore for ROOutput<H> {
         // Populate the u32 results vector
         for i in 0..results.as_ref().len() {
             let mut nxt = [0; 4];
-            for i in 0..4 {
-                nxt[i] = *iter.next().expect(
+            for b in nxt.iter_mut() {
+                *b = *iter.next().expect(
                     "Block result should match raw result in byte length",
                 );
             }
@@ -98,7 +102,8 @@ pub trait RO {
     /// Makes a simple query.
     fn query(i: &[u8]) -> ROOutput<Self>;
 
-    /// Makes a query of sequences of sequences. Often simpler to call in practice.
+    /// Makes a query of sequences of sequences. Often simpler to call in
+    /// practice.
     fn seq_query(i: &[&[u8]]) -> ROOutput<Self> {
         let mut vec = Vec::new();
         for inp in i.iter() {
@@ -0,0 +1,115 @@
+use crate::usrs::ImportError;
+use ff::{PrimeField, PrimeFieldRepr};
+use group::{CurveAffine, EncodedPoint};
+use std::io::{self, Read, Write};
+
+/// A type with a canonical binary encoding.
+///
+/// Group elements are encoded compressed, field elements as 32 little-endian
+/// bytes, and integers little-endian.
+pub trait Serializable: Sized {
+    /// Writes the canonical encoding.
+    fn write<W: Write>(&self, out: W) -> io::Result<()>;
+    /// Reads a canonical encoding, rejecting invalid group and field elements.
+    fn read<R: Read>(inp: R) -> Result<Self, ImportError>;
+}
+
+/// Writes a compressed curve point.
+pub fn write_point<C: CurveAffine, W: Write>(
+    p: &C,
+    mut out: W,
+) -> io::Result<()> {
+    out.write_all(p.into_compressed().as_ref())
+}
+
+/// Reads a compressed curve point, checking it lies in the prime order
+/// subgroup.
+pub fn read_point<C: CurveAffine, R: Read>(
+    mut inp: R,
+) -> Result<C, ImportError> {
+    let mut p = C::Compressed::empty();
+    inp.read_exact(p.as_mut())?;
+    Ok(p.into_affine()?)
+}
+
+/// Writes a field element.
+pub fn write_scalar<F: PrimeField, W: Write>(f: &F, out: W) -> io::Result<()> {
+    f.into_repr().write_le(out)
+}
+
+/// Reads a field element, rejecting non-canonical encodings.
+pub fn read_scalar<F: PrimeField, R: Read>(inp: R) -> Result<F, ImportError> {
+    let mut repr = F::Repr::default();
+    repr.read_le(inp)?;
+    Ok(F::from_repr(repr)?)
+}
+
+impl Serializable for () {
+    fn write<W: Write>(&self, _: W) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn read<R: Read>(_: R) -> Result<Self, ImportError> {
+        Ok(())
+    }
+}
+
+impl Serializable for u16 {
+    fn write<W: Write>(&self, mut out: W) -> io::Result<()> {
+        out.write_all(&self.to_le_bytes())
+    }
+
+    fn read<R: Read>(mut inp: R) -> Result<Self, ImportError> {
+        let mut buf = [0u8; 2];
+        inp.read_exact(&mut buf)?;
+        Ok(u16::from_le_bytes(buf))
+    }
+}
+
+impl<A: Serializable, B: Serializable> Serializable for (A, B) {
+    fn write<W: Write>(&self, mut out: W) -> io::Result<()> {
+        self.0.write(&mut out)?;
+        self.1.write(&mut out)
+    }
+
+    fn read<R: Read>(mut inp: R) -> Result<Self, ImportError> {
+        Ok((A::read(&mut inp)?, B::read(&mut inp)?))
+    }
+}
+
+impl<A: Serializable, B: Serializable, C: Serializable> Serializable
+    for (A, B, C)
+{
+    fn write<W: Write>(&self, mut out: W) -> io::Result<()> {
+        self.0.write(&mut out)?;
+        self.1.write(&mut out)?;
+        self.2.write(&mut out)
+    }
+
+    fn read<R: Read>(mut inp: R) -> Result<Self, ImportError> {
+        Ok((A::read(&mut inp)?, B::read(&mut inp)?, C::read(&mut inp)?))
+    }
+}
+
+/// Vectors are prefixed with their length as a `u64`.
+impl<T: Serializable> Serializable for Vec<T> {
+    fn write<W: Write>(&self, mut out: W) -> io::Result<()> {
+        out.write_all(&(self.len() as u64).to_le_bytes())?;
+        for x in self.iter() {
+            x.write(&mut out)?;
+        }
+        Ok(())
+    }
+
+    fn read<R: Read>(mut inp: R) -> Result<Self, ImportError> {
+        let mut len = [0u8; 8];
+        inp.read_exact(&mut len)?;
+        // Elements are read one at a time, so a bogus length cannot force a
+        // large allocation before the input runs out.
+        let mut res = Vec::new();
+        for _ in 0..u64::from_le_bytes(len) {
+            res.push(T::read(&mut inp)?);
+        }
+        Ok(res)
+    }
+}
@@ -0,0 +1,562 @@
+//! Out-of-core processing of exported SRS files, for dimensions too large to
+//! hold in memory.
+//!
+//! These functions read a versioned SRS file, as written by [`USRS::export`], a
+//! chunk of points at a time, so that only a chunk of each vector is ever held
+//! in memory.
+//!
+//! [`USRS::export`]: ../usrs/struct.USRS.html#method.export
+
+use crate::multiexp::multiexp;
+use crate::usrs::{
+    curve_id, encode_points, read_points_hashed, ImportError, SRSHeader,
+    StructureCheck, Trapdoor, VerificationError, SRS_VERSION,
+};
+use crate::util::{batch_exp, random_scalars};
+use ff::Field;
+use group::{CurveAffine, CurveProjective, EncodedPoint};
+use pairing::{Engine, PairingCurveAffine};
+use rand::{CryptoRng, Rng};
+use sha3::{Digest, Sha3_256};
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+/// A reasonable default number of points to hold in memory at once.
+pub const DEFAULT_CHUNK_SIZE: usize = 1 << 16;
+
+/// An error encountered while verifying an SRS file.
+#[derive(Debug)]
+pub enum StreamError {
+    /// The file could not be read.
+    Import(ImportError),
+    /// The SRS in the file is malformed.
+    Verification(VerificationError),
+}
+
+impl fmt::Display for StreamError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StreamError::Import(e) => write!(f, "{}", e),
+            StreamError::Verification(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for StreamError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            StreamError::Import(e) => Some(e),
+            StreamError::Verification(e) => Some(e),
+        }
+    }
+}
+
+impl From<ImportError> for StreamError {
+    fn from(e: ImportError) -> Self {
+        StreamError::Import(e)
+    }
+}
+
+impl From<VerificationError> for StreamError {
+    fn from(e: VerificationError) -> Self {
+        StreamError::Verification(e)
+    }
+}
+
+impl From<io::Error> for StreamError {
+    fn from(e: io::Error) -> Self {
+        StreamError::Import(e.into())
+    }
+}
+
+/// Reads the header of an SRS file, and starts its digest.
+fn read_header<E: Engine, R: Read>(
+    inp: &mut R,
+) -> Result<(SRSHeader, Sha3_256), ImportError> {
+    let header = SRSHeader::read(inp)?;
+    if header.curve != curve_id::<E>() {
+        return Err(ImportError::CurveMismatch);
+    }
+    let mut sha = Sha3_256::new();
+    sha.input((header.d as u64).to_le_bytes());
+    Ok((header, sha))
+}
+
+/// Reads the `n` points of one SRS vector, passing each chunk of at most
+/// `chunk` points to `f` along with the index of its first point. The points
+/// are hashed into `sha` as they are stored.
+fn for_each_chunk<G: CurveAffine, R: Read, F>(
+    inp: &mut R,
+    n: usize,
+    compressed: bool,
+    chunk: usize,
+    sha: &mut Sha3_256,
+    mut f: F,
+) -> Result<(), ImportError>
+where
+    F: FnMut(usize, Vec<G>) -> Result<(), ImportError>,
+{
+    assert!(chunk > 0);
+    for start in (0..n).step_by(chunk) {
+        let len = chunk.min(n - start);
+        let points = if compressed {
+            read_points_hashed::<G::Compressed, _>(inp, len, sha)?
+        } else {
+            read_points_hashed::<G::Uncompressed, _>(inp, len, sha)?
+        };
+        f(start, points)?;
+    }
+    Ok(())
+}
+
+/// Writes encoded points to `out`, hashing them into `sha`.
+fn write_hashed<P: EncodedPoint, W: Write>(
+    out: &mut W,
+    sha: &mut Sha3_256,
+    points: &[P],
+) -> Result<(), ImportError> {
+    for p in points.iter() {
+        sha.input(p);
+        out.write_all(p.as_ref())?;
+    }
+    Ok(())
+}
+
+/// Streams one SRS vector, raising its `i`-th point to `first * step^i`. The
+/// point at index `unset`, if any, is replaced with the generator.
+#[allow(clippy::too_many_arguments)]
+fn permute_vector<G: CurveAffine, R: Read, W: Write>(
+    inp: &mut R,
+    out: &mut W,
+    compressed: bool,
+    n: usize,
+    chunk: usize,
+    first: G::Scalar,
+    step: G::Scalar,
+    unset: Option<usize>,
+    shas: (&mut Sha3_256, &mut Sha3_256),
+) -> Result<(), ImportError> {
+    let (in_sha, out_sha) = shas;
+    let mut scalar = first;
+    for_each_chunk::<G, _, _>(
+        inp,
+        n,
+        compressed,
+        chunk,
+        in_sha,
+        |start, points| {
+            let scalars = points
+                .iter()
+                .map(|_| {
+                    let s = scalar;
+                    scalar.mul_assign(&step);
+                    s
+                })
+                .collect::<Vec<_>>();
+            let mut res = batch_exp(&points, &scalars);
+            if let Some(i) = unset {
+                if i >= start && i < start + res.len() {
+                    res[i - start] = G::one();
+                }
+            }
+            if compressed {
+                write_hashed(
+                    out,
+                    out_sha,
+                    &encode_points::<G::Compressed>(&res),
+                )
+            } else {
+                write_hashed(
+                    out,
+                    out_sha,
+                    &encode_points::<G::Uncompressed>(&res),
+                )
+            }
+        },
+    )
+}
+
+/// Applies a trapdoor permutation to the SRS file read from `inp`, writing the
+/// result to `out`.
+///
+/// This produces the same SRS as [`USRS::permute`], in the same encoding as the
+/// input, while holding at most `chunk` points in memory at a time. The header
+/// is written last, once the digest of the result is known, so `out` must be
+/// seekable.
+///
+/// As the input's digest can only be checked once all of it has been read, a
+/// mismatch is reported after the output has been written; the output must then
+/// be discarded.
+///
+/// [`USRS::permute`]: ../usrs/struct.USRS.html#method.permute
+pub fn permute<E: Engine, R: Read, W: Write + Seek>(
+    mut inp: R,
+    mut out: W,
+    trapdoor: &Trapdoor<E>,
+    chunk: usize,
+) -> Result<(), ImportError> {
+    let (header, mut in_sha) = read_header::<E, _>(&mut inp)?;
+    let d = header.d;
+    let n = 2 * d + 1;
+    let mut out_header = SRSHeader {
+        version: SRS_VERSION,
+        digest: [0u8; 32],
+        ..header.clone()
+    };
+    let start = out.stream_position()?;
+    out_header.write(&mut out)?;
+    let mut out_sha = Sha3_256::new();
+    out_sha.input((d as u64).to_le_bytes());
+    // y^{-d} and \beta y^{-d}
+    let y = trapdoor.x;
+    let neg_yd = y
+        .inverse()
+        .expect("trapdoor may not be zero")
+        .pow([d as u64]);
+    let mut neg_byd = neg_yd;
+    neg_byd.mul_assign(&trapdoor.alpha);
+    let c = header.compressed;
+    permute_vector::<E::G1Affine, _, _>(
+        &mut inp,
+        &mut out,
+        c,
+        n,
+        chunk,
+        neg_yd,
+        y,
+        None,
+        (&mut in_sha, &mut out_sha),
+    )?;
+    permute_vector::<E::G1Affine, _, _>(
+        &mut inp,
+        &mut out,
+        c,
+        n,
+        chunk,
+        neg_byd,
+        y,
+        Some(d),
+        (&mut in_sha, &mut out_sha),
+    )?;
+    permute_vector::<E::G2Affine, _, _>(
+        &mut inp,
+        &mut out,
+        c,
+        n,
+        chunk,
+        neg_yd,
+        y,
+        None,
+        (&mut in_sha, &mut out_sha),
+    )?;
+    permute_vector::<E::G2Affine, _, _>(
+        &mut inp,
+        &mut out,
+        c,
+        n,
+        chunk,
+        neg_byd,
+        y,
+        None,
+        (&mut in_sha, &mut out_sha),
+    )?;
+    if in_sha.result().as_slice() != header.digest {
+        return Err(ImportError::DigestMismatch);
+    }
+    out_header.digest.copy_from_slice(out_sha.result().as_ref());
+    let end = out.stream_position()?;
+    out.seek(SeekFrom::Start(start))?;
+    out_header.write(&mut out)?;
+    out.seek(SeekFrom::Start(end))?;
+    Ok(())
+}
+
+/// Random linear combinations `\sum_i r_i p_i` and `\sum_i r_i p_{i + 1}` over
+/// the consecutive pairs of points `(p_i, p_{i + 1})` of one SRS vector.
+struct PowerSums<G: CurveAffine> {
+    lo: G::Projective,
+    hi: G::Projective,
+    /// The coefficient of the pair straddling the previous and the current
+    /// chunk.
+    carry: G::Scalar,
+}
+
+impl<G: CurveAffine> PowerSums<G> {
+    fn new() -> Self {
+        PowerSums {
+            lo: G::Projective::zero(),
+            hi: G::Projective::zero(),
+            carry: G::Scalar::zero(),
+        }
+    }
+
+    /// Adds a chunk of points starting at index `start` of a vector of `n`
+    /// points. Pairs starting at an index in `skip` are left out.
+    fn add<R: Rng + CryptoRng + ?Sized>(
+        &mut self,
+        start: usize,
+        points: &[G],
+        n: usize,
+        skip: &[usize],
+        rng: &mut R,
+    ) {
+        let mut rnd = random_scalars::<G::Scalar, _>(points.len(), rng);
+        for (i, r) in rnd.iter_mut().enumerate() {
+            if start + i + 1 >= n || skip.contains(&(start + i)) {
+                *r = G::Scalar::zero();
+            }
+        }
+        // Point i is the second of the pair starting at i - 1.
+        let mut shifted = Vec::with_capacity(points.len());
+        shifted.push(self.carry);
+        shifted.extend_from_slice(&rnd[..points.len() - 1]);
+        self.carry = rnd[points.len() - 1];
+        self.lo.add_assign(&multiexp(points.iter(), rnd.iter()));
+        self.hi.add_assign(&multiexp(points.iter(), shifted.iter()));
+    }
+}
+
+/// Verifies the structure of the SRS file read from `inp`, as
+/// [`USRS::verify_structure`] does, while holding at most `chunk` points in
+/// memory at a time.
+///
+/// The file's digest is checked as well.
+///
+/// [`USRS::verify_structure`]: ../usrs/struct.USRS.html#method.verify_structure
+pub fn verify_structure<E: Engine, R: Read, G: Rng + CryptoRng + ?Sized>(
+    mut inp: R,
+    chunk: usize,
+    rng: &mut G,
+) -> Result<(), StreamError> {
+    use VerificationError::Structure;
+    let (header, mut sha) = read_header::<E, _>(&mut inp)?;
+    let d = header.d;
+    let n = 2 * d + 1;
+    let c = header.compressed;
+    // The individual elements the pairing checks need.
+    let mut g_x = [E::G1Affine::zero(); 2];
+    let mut g_ax = [E::G1Affine::zero(); 3];
+    let mut h_x = [E::G2Affine::zero(); 3];
+    let mut h_ax = E::G2Affine::zero();
+    fn pick<G: Copy>(start: usize, points: &[G], i: usize, into: &mut G) {
+        if i >= start && i < start + points.len() {
+            *into = points[i - start];
+        }
+    }
+    let mut g_sums = PowerSums::<E::G1Affine>::new();
+    for_each_chunk::<E::G1Affine, _, _>(
+        &mut inp,
+        n,
+        c,
+        chunk,
+        &mut sha,
+        |s, ps| {
+            pick(s, &ps, d, &mut g_x[0]);
+            pick(s, &ps, d + 1, &mut g_x[1]);
+            g_sums.add(s, &ps, n, &[], rng);
+            Ok(())
+        },
+    )?;
+    // g^\alpha is undefined, so the pairs including it are skipped.
+    for_each_chunk::<E::G1Affine, _, _>(
+        &mut inp,
+        n,
+        c,
+        chunk,
+        &mut sha,
+        |s, ps| {
+            for (j, g) in g_ax.iter_mut().enumerate() {
+                pick(s, &ps, d - 1 + j, g);
+            }
+            g_sums.add(s, &ps, n, &[d - 1, d], rng);
+            Ok(())
+        },
+    )?;
+    let mut h_sums = PowerSums::<E::G2Affine>::new();
+    for_each_chunk::<E::G2Affine, _, _>(
+        &mut inp,
+        n,
+        c,
+        chunk,
+        &mut sha,
+        |s, ps| {
+            for (j, h) in h_x.iter_mut().enumerate() {
+                pick(s, &ps, d + j, h);
+            }
+            h_sums.add(s, &ps, n, &[], rng);
+            Ok(())
+        },
+    )?;
+    for_each_chunk::<E::G2Affine, _, _>(
+        &mut inp,
+        n,
+        c,
+        chunk,
+        &mut sha,
+        |s, ps| {
+            pick(s, &ps, d + 1, &mut h_ax);
+            h_sums.add(s, &ps, n, &[], rng);
+            Ok(())
+        },
+    )?;
+    if sha.result().as_slice() != header.digest {
+        return Err(ImportError::DigestMismatch.into());
+    }
+    let g = E::G1Affine::one();
+    let h = E::G2Affine::one();
+    let e = E::pairing;
+    if g_x[0] != g || h_x[0] != h {
+        return Err(Structure(StructureCheck::Generators).into());
+    }
+    if g_ax[1] != g {
+        return Err(Structure(StructureCheck::UnsetAlpha).into());
+    }
+    let t_ax = e(g, h_ax);
+    if t_ax != e(g_ax[2], h) {
+        return Err(Structure(StructureCheck::Alpha).into());
+    }
+    if e(g, h_x[1]) != e(g_x[1], h) {
+        return Err(Structure(StructureCheck::X).into());
+    }
+    // constrain e(g, h)^{\alpha\beta (xy)^{-1}}
+    if t_ax != e(g_ax[0], h_x[2]) {
+        return Err(Structure(StructureCheck::InverseX).into());
+    }
+    let mut neg_hx = h_x[1];
+    neg_hx.negate();
+    let mut neg_gx = g_x[1];
+    neg_gx.negate();
+    let table = [
+        (g_sums.lo.into_affine().prepare(), neg_hx.prepare()),
+        (g_sums.hi.into_affine().prepare(), h.prepare()),
+        (neg_gx.prepare(), h_sums.lo.into_affine().prepare()),
+        (g.prepare(), h_sums.hi.into_affine().prepare()),
+    ];
+    let table_ref = table.iter().map(|(a, b)| (a, b)).collect::<Vec<_>>();
+    let lp = E::miller_loop(&table_ref[..]);
+    if E::final_exponentiation(&lp).unwrap() != E::Fqk::one() {
+        return Err(Structure(StructureCheck::Powers).into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ro::RO;
+    use crate::usrs::USRS;
+    use ff::PrimeField;
+    use pairing::bls12_381::{Bls12, Fr, G1Affine};
+    use std::io::Cursor;
+
+    #[test]
+    fn permute_matches_in_memory() {
+        let mut rng = Sha3_256::query(b"stream test").into_rng();
+        let srs = USRS::<Bls12>::new(2).permute(&rng.gen());
+        let trapdoor = rng.gen();
+        let expected = srs.permute(&trapdoor);
+        for compressed in [true, false].iter() {
+            let mut inp = Vec::new();
+            if *compressed {
+                srs.export(&mut inp).unwrap();
+            } else {
+                srs.export_uncompressed(&mut inp).unwrap();
+            }
+            let mut out = Cursor::new(Vec::new());
+            permute::<Bls12, _, _>(&inp[..], &mut out, &trapdoor, 3).unwrap();
+            let out = out.into_inner();
+            assert_eq!(
+                SRSHeader::read(&out[..]).unwrap(),
+                expected.header(*compressed)
+            );
+            verify_structure::<Bls12, _, _>(&out[..], 3, &mut rng).unwrap();
+            let imported = USRS::<Bls12>::import(&out[..]).unwrap();
+            assert_eq!(imported.digest(), expected.digest());
+        }
+    }
+
+    /// Adds the generator to `p`.
+    fn corrupt<G: CurveAffine>(p: &mut G) {
+        let mut sum = p.into_projective();
+        sum.add_assign_mixed(&G::one());
+        *p = sum.into_affine();
+    }
+
+    /// Streams `srs` through verification in chunks of 3 points.
+    fn verify_chunked(srs: &USRS<Bls12>) -> Result<(), StreamError> {
+        let mut rng = Sha3_256::query(b"stream verify").into_rng();
+        let mut buf = Vec::new();
+        srs.export(&mut buf).unwrap();
+        verify_structure::<Bls12, _, _>(&buf[..], 3, &mut rng)
+    }
+
+    #[test]
+    fn verify_rejects_corrupted_elements() {
+        use StructureCheck::*;
+        let mut rng = Sha3_256::query(b"stream corrupt").into_rng();
+        // With d = 4 and chunks of 3, chunks start at 0, 3 and 6, and the
+        // pairs (2, 3) and (5, 6) span chunk boundaries.
+        let srs = USRS::<Bls12>::new(4).permute(&rng.gen());
+        verify_chunked(&srs).unwrap();
+        type Corruption = (fn(&mut USRS<Bls12>), StructureCheck);
+        let cases: [Corruption; 11] = [
+            (|s| corrupt(&mut s.g_x[3]), Powers),
+            (|s| corrupt(&mut s.g_x[2]), Powers),
+            (|s| corrupt(&mut s.g_x[4]), Generators),
+            (|s| corrupt(&mut s.g_x[5]), X),
+            // Only the pairs (3, 4) and (4, 5) of g_ax are skipped.
+            (|s| corrupt(&mut s.g_ax[2]), Powers),
+            (|s| corrupt(&mut s.g_ax[6]), Powers),
+            (|s| corrupt(&mut s.g_ax[3]), InverseX),
+            (|s| corrupt(&mut s.g_ax[4]), UnsetAlpha),
+            (|s| corrupt(&mut s.h_x[3]), Powers),
+            (|s| corrupt(&mut s.h_ax[3]), Powers),
+            (|s| corrupt(&mut s.h_ax[6]), Powers),
+        ];
+        // Scaling every element from a chunk boundary on keeps all pairs within
+        // chunks consistent, so only the pair carried across the boundary
+        // catches it.
+        let scale = |ps: &mut [G1Affine]| {
+            let two = Fr::from_str("2").unwrap();
+            for p in ps.iter_mut() {
+                *p = p.mul(two).into_affine();
+            }
+        };
+        let mut bad = srs.clone();
+        scale(&mut bad.g_ax[6..]);
+        let mut bad_head = srs.clone();
+        scale(&mut bad_head.g_ax[..3]);
+        for bad in [bad, bad_head].iter() {
+            assert!(matches!(
+                verify_chunked(bad),
+                Err(StreamError::Verification(VerificationError::Structure(
+                    Powers
+                )))
+            ));
+        }
+        for (f, check) in cases.iter() {
+            let mut bad = srs.clone();
+            f(&mut bad);
+            match verify_chunked(&bad) {
+                Err(StreamError::Verification(
+                    VerificationError::Structure(found),
+                )) => assert_eq!(found, *check),
+                _ => panic!("expected the {} check to fail", check),
+            }
+        }
+    }
+
+    #[test]
+    fn verify_rejects_corrupted_digest() {
+        let mut rng = Sha3_256::query(b"stream digest").into_rng();
+        let srs = USRS::<Bls12>::new(2).permute(&rng.gen());
+        let mut buf = Vec::new();
+        srs.export(&mut buf).unwrap();
+        buf[SRSHeader::LEN - 1] ^= 1;
+        assert!(matches!(
+            verify_structure::<Bls12, _, _>(&buf[..], 3, &mut rng),
+            Err(StreamError::Import(ImportError::DigestMismatch))
+        ));
+    }
+}
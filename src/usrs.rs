@@ -1,23 +1,572 @@
-use crate::poe::{CurvePair, FieldPair, NIZK};
-use crate::util::{multiexp, Split};
-use ff::{Field, ScalarEngine};
-use group::{CurveAffine, CurveProjective};
+use crate::compose::{AndComposition, Composed};
+use crate::multiexp::multiexp;
+use crate::poe::{
+    BatchVerify, CrossGroupSigmaProtocol, CrossGroupStatement, CurvePair,
+    FieldElement, FieldPair, Relation, SerializableNIZK, NIZK,
+};
+use crate::serialize::{read_point, write_point, Serializable};
+use crate::util::{batch_exp, random_scalars, Parallelism, Split};
+use ff::{Field, PrimeFieldDecodingError, ScalarEngine};
+use group::{CurveAffine, CurveProjective, EncodedPoint, GroupDecodingError};
 use pairing::{Engine, PairingCurveAffine};
 use rand::distributions::{Distribution, Standard};
 use rand::{CryptoRng, Rng};
-use rayon::iter::once;
 use rayon::prelude::*;
-use std::io::{self, Write};
+use sha3::{Digest, Sha3_256};
+use std::convert::{TryFrom, TryInto};
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::marker::PhantomData;
 
-/// `try!` replacement for boolean conditions.
+/// `try!` replacement for boolean conditions, failing with the given error.
 macro_rules! check {
-    ($x:expr) => {{
+    ($x:expr, $err:expr) => {{
         if !$x {
-            return false;
+            return Err($err);
         }
     }};
 }
 
+/// A check of the SRS structure.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StructureCheck {
+    /// The vectors have the lengths required by the dimension.
+    Length,
+    /// `g^{x^0}` and `h^{x^0}` are the generators.
+    Generators,
+    /// The slot of `g^\alpha` holds the generator, rather than the secret
+    /// `g^\alpha`.
+    UnsetAlpha,
+    /// `g^{\alpha x}` and `h^{\alpha x}` share an exponent.
+    Alpha,
+    /// `g^x` and `h^x` share an exponent.
+    X,
+    /// `g^{\alpha x^{-1}}` is consistent with `h^{\alpha x}` and `h^{x^2}`.
+    InverseX,
+    /// Each element is the previous one raised to `x`.
+    Powers,
+}
+
+impl fmt::Display for StructureCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StructureCheck::Length => write!(f, "vector lengths"),
+            StructureCheck::Generators => write!(f, "generators"),
+            StructureCheck::UnsetAlpha => write!(f, "unset alpha"),
+            StructureCheck::Alpha => write!(f, "alpha pairing"),
+            StructureCheck::X => write!(f, "x pairing"),
+            StructureCheck::InverseX => write!(f, "inverse x pairing"),
+            StructureCheck::Powers => write!(f, "powers of x"),
+        }
+    }
+}
+
+/// The reason an SRS or update failed to verify.
+///
+/// Where an update index is given, it is the position of the offending update
+/// in an [`AggregateUpdate`], and always zero for a single [`Update`].
+///
+/// [`AggregateUpdate`]: struct.AggregateUpdate.html
+/// [`Update`]: struct.Update.html
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VerificationError {
+    /// The updated SRS has a different dimension from the SRS it updates.
+    Degree {
+        /// The dimension of the original SRS.
+        expected: usize,
+        /// The dimension of the updated SRS.
+        found: usize,
+    },
+    /// The update's `g^y` or `g^{\beta y}` is the generator.
+    TrivialUpdate(usize),
+    /// The update's proof of knowledge of exponent is invalid.
+    ProofOfExponent(usize),
+    /// The proofs of knowledge of exponent fail as a batch, although each
+    /// verifies on its own. This points to a faulty [`BatchVerify`]
+    /// implementation rather than an invalid update.
+    ///
+    /// [`BatchVerify`]: ../poe/trait.BatchVerify.html
+    BatchProof,
+    /// The update's `h^x` is not linked to the previous SRS by `g^y`.
+    XLink(usize),
+    /// The update's `h^{\alpha x}` is not linked to the previous SRS by
+    /// `g^{\beta y}`.
+    AlphaLink(usize),
+    /// The updates do not start from the initial SRS.
+    Initial,
+    /// The updates are not linked to each other. These links are checked in
+    /// batch, so the offending update is not known.
+    Chain,
+    /// The resulting SRS is malformed.
+    Structure(StructureCheck),
+}
+
+impl fmt::Display for VerificationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerificationError::Degree { expected, found } => write!(
+                f,
+                "SRS dimension {} does not match expected {}",
+                found, expected
+            ),
+            VerificationError::TrivialUpdate(i) => {
+                write!(f, "update {} is trivial", i)
+            }
+            VerificationError::ProofOfExponent(i) => {
+                write!(f, "update {} has an invalid proof of exponent", i)
+            }
+            VerificationError::BatchProof => write!(
+                f,
+                "proofs of exponent fail as a batch but verify individually"
+            ),
+            VerificationError::XLink(i) => {
+                write!(f, "update {} does not link h^x to the previous SRS", i)
+            }
+            VerificationError::AlphaLink(i) => write!(
+                f,
+                "update {} does not link h^{{alpha x}} to the previous SRS",
+                i
+            ),
+            VerificationError::Initial => {
+                write!(f, "updates do not start from the initial SRS")
+            }
+            VerificationError::Chain => {
+                write!(f, "updates are not linked to each other")
+            }
+            VerificationError::Structure(c) => {
+                write!(f, "SRS structure check failed: {}", c)
+            }
+        }
+    }
+}
+
+impl Error for VerificationError {}
+
+/// Magic bytes opening a versioned SRS file.
+pub const SRS_MAGIC: [u8; 8] = *b"PISTSRS\0";
+/// The SRS file format version written by this library.
+pub const SRS_VERSION: u16 = 1;
+/// Header flag marking point encodings as compressed.
+const SRS_FLAG_COMPRESSED: u8 = 0x01;
+
+/// An error encountered while importing a serialized SRS, update or proof.
+#[derive(Debug)]
+pub enum ImportError {
+    /// The input ended before the full value was read.
+    Truncated,
+    /// The underlying reader failed.
+    Io(io::Error),
+    /// A group element was not a valid encoding of a point in the prime order
+    /// subgroup.
+    InvalidEncoding(GroupDecodingError),
+    /// A field element was not canonically encoded.
+    InvalidScalar(PrimeFieldDecodingError),
+    /// The encoded dimension is not a valid SRS dimension.
+    InvalidLength(u64),
+    /// The input is not a versioned SRS file.
+    BadMagic,
+    /// The file format version is not supported.
+    UnsupportedVersion(u16),
+    /// The file sets header flags this version does not understand.
+    UnsupportedFlags(u8),
+    /// The file was created for a different curve.
+    CurveMismatch,
+    /// The SRS contents do not match the digest recorded in the header.
+    DigestMismatch,
+    /// The SRS is valid, but not in the canonical encoding: a versioned file
+    /// with compressed points.
+    NonCanonical,
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Truncated => write!(f, "input is truncated"),
+            ImportError::Io(e) => write!(f, "I/O error: {}", e),
+            ImportError::InvalidEncoding(e) => {
+                write!(f, "invalid group element: {}", e)
+            }
+            ImportError::InvalidScalar(e) => {
+                write!(f, "invalid field element: {}", e)
+            }
+            ImportError::InvalidLength(d) => {
+                write!(f, "invalid SRS dimension {}", d)
+            }
+            ImportError::BadMagic => write!(f, "not a versioned SRS file"),
+            ImportError::UnsupportedVersion(v) => {
+                write!(f, "unsupported SRS format version {}", v)
+            }
+            ImportError::UnsupportedFlags(fl) => {
+                write!(f, "unsupported SRS header flags {:#04x}", fl)
+            }
+            ImportError::CurveMismatch => {
+                write!(f, "SRS was created for a different curve")
+            }
+            ImportError::DigestMismatch => {
+                write!(f, "SRS does not match its recorded digest")
+            }
+            ImportError::NonCanonical => {
+                write!(f, "SRS is not in the canonical encoding")
+            }
+        }
+    }
+}
+
+impl Error for ImportError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ImportError::Io(e) => Some(e),
+            ImportError::InvalidEncoding(e) => Some(e),
+            ImportError::InvalidScalar(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ImportError {
+    fn from(e: io::Error) -> Self {
+        if e.kind() == io::ErrorKind::UnexpectedEof {
+            ImportError::Truncated
+        } else {
+            ImportError::Io(e)
+        }
+    }
+}
+
+impl From<GroupDecodingError> for ImportError {
+    fn from(e: GroupDecodingError) -> Self {
+        ImportError::InvalidEncoding(e)
+    }
+}
+
+impl From<PrimeFieldDecodingError> for ImportError {
+    fn from(e: PrimeFieldDecodingError) -> Self {
+        ImportError::InvalidScalar(e)
+    }
+}
+
+/// Checks a serialized dimension, returning it as a `usize`.
+fn check_dimension(d: u64) -> Result<usize, ImportError> {
+    match usize::try_from(d) {
+        Ok(d) if (2..=(usize::MAX - 1) / 4).contains(&d) => Ok(d),
+        _ => Err(ImportError::InvalidLength(d)),
+    }
+}
+
+/// Reads `n` encoded points, and decodes them in parallel.
+///
+/// Points are read one at a time, so a bogus `n` cannot force a large
+/// allocation before the input runs out.
+pub(crate) fn read_points<P: EncodedPoint, R: Read>(
+    inp: &mut R,
+    n: usize,
+) -> Result<Vec<P::Affine>, ImportError> {
+    decode_points(&read_encoded::<P, _>(inp, n)?)
+}
+
+/// Reads `n` encoded points as [`read_points`] does, hashing their encodings
+/// into `sha`.
+///
+/// [`read_points`]: fn.read_points.html
+pub(crate) fn read_points_hashed<P: EncodedPoint, R: Read>(
+    inp: &mut R,
+    n: usize,
+    sha: &mut Sha3_256,
+) -> Result<Vec<P::Affine>, ImportError> {
+    let encoded = read_encoded::<P, _>(inp, n)?;
+    for p in encoded.iter() {
+        sha.input(p);
+    }
+    decode_points(&encoded)
+}
+
+/// Reads `n` encoded points, one at a time.
+fn read_encoded<P: EncodedPoint, R: Read>(
+    inp: &mut R,
+    n: usize,
+) -> Result<Vec<P>, ImportError> {
+    let mut encoded = Vec::new();
+    for _ in 0..n {
+        let mut p = P::empty();
+        inp.read_exact(p.as_mut())?;
+        encoded.push(p);
+    }
+    Ok(encoded)
+}
+
+/// Decodes points in parallel.
+fn decode_points<P: EncodedPoint>(
+    encoded: &[P],
+) -> Result<Vec<P::Affine>, ImportError> {
+    encoded
+        .par_iter()
+        .map(|p| p.into_affine().map_err(ImportError::from))
+        .collect()
+}
+
+/// Encodes points in parallel.
+pub(crate) fn encode_points<P: EncodedPoint>(points: &[P::Affine]) -> Vec<P> {
+    points.par_iter().map(|p| P::from_affine(*p)).collect()
+}
+
+/// Identifies the curves of a pairing engine by hashing their generators.
+pub fn curve_id<E: Engine>() -> [u8; 8] {
+    let mut sha = Sha3_256::new();
+    sha.input(E::G1Affine::one().into_uncompressed());
+    sha.input(E::G2Affine::one().into_uncompressed());
+    let mut id = [0u8; 8];
+    id.copy_from_slice(&sha.result()[..8]);
+    id
+}
+
+/// The header of a versioned SRS file.
+///
+/// All integers are little-endian. The header is laid out as:
+///
+/// | bytes | field                              |
+/// |-------|------------------------------------|
+/// | 8     | magic bytes, [`SRS_MAGIC`]         |
+/// | 2     | format version                     |
+/// | 8     | curve identifier, see [`curve_id`] |
+/// | 1     | flags; bit 0 marks compression     |
+/// | 8     | the dimension `d`                  |
+/// | 32    | the SRS digest                     |
+///
+/// It is followed by the points of `g_x`, `g_ax`, `h_x` and `h_ax`, in that
+/// order. The digest is the SHA3-256 hash of the dimension, as a `u64`,
+/// followed by the points exactly as stored, so it can be checked without
+/// re-encoding them. For compressed files, this is [`digest`]. Later versions
+/// may change everything after the version field.
+///
+/// Powers of tau files share this layout under their own magic bytes; see
+/// [`PowersOfTau::export`].
+///
+/// [`SRS_MAGIC`]: constant.SRS_MAGIC.html
+/// [`curve_id`]: fn.curve_id.html
+/// [`digest`]: struct.USRS.html#method.digest
+/// [`PowersOfTau::export`]: ../kzg/struct.PowersOfTau.html#method.export
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SRSHeader {
+    /// The magic bytes identifying the file type, [`SRS_MAGIC`] for an SRS.
+    ///
+    /// [`SRS_MAGIC`]: constant.SRS_MAGIC.html
+    pub magic: [u8; 8],
+    /// The format version.
+    pub version: u16,
+    /// The identifier of the curves the SRS is over.
+    pub curve: [u8; 8],
+    /// Whether points are stored compressed.
+    pub compressed: bool,
+    /// The dimension of the SRS.
+    pub d: usize,
+    /// The digest of the SRS.
+    pub digest: [u8; 32],
+}
+
+impl SRSHeader {
+    /// The length of the encoded header in bytes.
+    pub const LEN: usize = 59;
+
+    /// Writes the header.
+    pub fn write<W: Write>(&self, mut out: W) -> io::Result<()> {
+        let flags = if self.compressed {
+            SRS_FLAG_COMPRESSED
+        } else {
+            0
+        };
+        out.write_all(&self.magic)?;
+        out.write_all(&self.version.to_le_bytes())?;
+        out.write_all(&self.curve)?;
+        out.write_all(&[flags])?;
+        out.write_all(&(self.d as u64).to_le_bytes())?;
+        out.write_all(&self.digest)
+    }
+
+    /// Reads the header of an SRS file, including the magic bytes.
+    pub fn read<R: Read>(inp: R) -> Result<Self, ImportError> {
+        Self::read_with(inp, SRS_MAGIC, SRS_VERSION, check_dimension)
+    }
+
+    /// Reads the header of a file with the given magic bytes and version,
+    /// including the magic bytes. `check_len` validates the dimension.
+    pub(crate) fn read_with<R: Read>(
+        mut inp: R,
+        magic: [u8; 8],
+        version: u16,
+        check_len: fn(u64) -> Result<usize, ImportError>,
+    ) -> Result<Self, ImportError> {
+        let mut found = [0u8; 8];
+        inp.read_exact(&mut found)?;
+        if found != magic {
+            return Err(ImportError::BadMagic);
+        }
+        Self::read_after_magic(inp, magic, version, check_len)
+    }
+
+    /// Reads the remainder of a header following the magic bytes.
+    fn read_after_magic<R: Read>(
+        mut inp: R,
+        magic: [u8; 8],
+        expected: u16,
+        check_len: fn(u64) -> Result<usize, ImportError>,
+    ) -> Result<Self, ImportError> {
+        let mut version = [0u8; 2];
+        inp.read_exact(&mut version)?;
+        let version = u16::from_le_bytes(version);
+        if version != expected {
+            return Err(ImportError::UnsupportedVersion(version));
+        }
+        let mut curve = [0u8; 8];
+        inp.read_exact(&mut curve)?;
+        let mut flags = [0u8; 1];
+        inp.read_exact(&mut flags)?;
+        if flags[0] & !SRS_FLAG_COMPRESSED != 0 {
+            return Err(ImportError::UnsupportedFlags(flags[0]));
+        }
+        let mut d = [0u8; 8];
+        inp.read_exact(&mut d)?;
+        let d = check_len(u64::from_le_bytes(d))?;
+        let mut digest = [0u8; 32];
+        inp.read_exact(&mut digest)?;
+        Ok(SRSHeader {
+            magic,
+            version,
+            curve,
+            compressed: flags[0] & SRS_FLAG_COMPRESSED != 0,
+            d,
+            digest,
+        })
+    }
+}
+
+type G1Compressed<E> = <<E as Engine>::G1Affine as CurveAffine>::Compressed;
+type G2Compressed<E> = <<E as Engine>::G2Affine as CurveAffine>::Compressed;
+type G1Uncompressed<E> = <<E as Engine>::G1Affine as CurveAffine>::Uncompressed;
+type G2Uncompressed<E> = <<E as Engine>::G2Affine as CurveAffine>::Uncompressed;
+
+/// The vectors of points in a file laid out as in [`SRSHeader`]: some in G1,
+/// followed by some in G2.
+///
+/// [`SRSHeader`]: struct.SRSHeader.html
+pub(crate) type PointVectors<G1, G2> = (Vec<Vec<G1>>, Vec<Vec<G2>>);
+
+/// Calls `f` on the encoding of each point of `gs`, then of `hs`.
+fn for_each_encoded<E: Engine>(
+    gs: &[&[E::G1Affine]],
+    hs: &[&[E::G2Affine]],
+    compressed: bool,
+    f: &mut dyn FnMut(&[u8]) -> io::Result<()>,
+) -> io::Result<()> {
+    if compressed {
+        for_each_encoded_as::<G1Compressed<E>, G2Compressed<E>>(gs, hs, f)
+    } else {
+        for_each_encoded_as::<G1Uncompressed<E>, G2Uncompressed<E>>(gs, hs, f)
+    }
+}
+
+fn for_each_encoded_as<P1: EncodedPoint, P2: EncodedPoint>(
+    gs: &[&[P1::Affine]],
+    hs: &[&[P2::Affine]],
+    f: &mut dyn FnMut(&[u8]) -> io::Result<()>,
+) -> io::Result<()> {
+    for v in gs.iter() {
+        for g in encode_points::<P1>(v) {
+            f(g.as_ref())?;
+        }
+    }
+    for v in hs.iter() {
+        for h in encode_points::<P2>(v) {
+            f(h.as_ref())?;
+        }
+    }
+    Ok(())
+}
+
+/// The digest recorded in the header of a file with dimension `d` and the
+/// points `gs`, then `hs`; see [`SRSHeader`].
+///
+/// [`SRSHeader`]: struct.SRSHeader.html
+pub(crate) fn points_digest<E: Engine>(
+    d: usize,
+    gs: &[&[E::G1Affine]],
+    hs: &[&[E::G2Affine]],
+    compressed: bool,
+) -> [u8; 32] {
+    let mut sha = Sha3_256::new();
+    sha.input((d as u64).to_le_bytes());
+    for_each_encoded::<E>(gs, hs, compressed, &mut |p| {
+        sha.input(p);
+        Ok(())
+    })
+    .expect("hashing cannot fail");
+    let mut res = [0u8; 32];
+    res.copy_from_slice(sha.result().as_ref());
+    res
+}
+
+/// Writes `header`, followed by the points `gs`, then `hs`, in the encoding it
+/// records.
+pub(crate) fn write_points_file<E: Engine, W: Write>(
+    mut out: W,
+    header: &SRSHeader,
+    gs: &[&[E::G1Affine]],
+    hs: &[&[E::G2Affine]],
+) -> io::Result<()> {
+    header.write(&mut out)?;
+    for_each_encoded::<E>(gs, hs, header.compressed, &mut |p| out.write_all(p))
+}
+
+/// Reads the points following `header`: vectors of the lengths in `g_lens` in
+/// G1, then of those in `h_lens` in G2. They are checked against the curve and
+/// digest recorded in the header.
+pub(crate) fn read_points_file<E: Engine, R: Read>(
+    inp: R,
+    header: &SRSHeader,
+    g_lens: &[usize],
+    h_lens: &[usize],
+) -> Result<PointVectors<E::G1Affine, E::G2Affine>, ImportError> {
+    if header.curve != curve_id::<E>() {
+        return Err(ImportError::CurveMismatch);
+    }
+    // The digest covers the points as stored, so they need not be re-encoded to
+    // check it.
+    let mut sha = Sha3_256::new();
+    sha.input((header.d as u64).to_le_bytes());
+    let res = if header.compressed {
+        read_vectors::<G1Compressed<E>, G2Compressed<E>, _>(
+            inp, g_lens, h_lens, &mut sha,
+        )?
+    } else {
+        read_vectors::<G1Uncompressed<E>, G2Uncompressed<E>, _>(
+            inp, g_lens, h_lens, &mut sha,
+        )?
+    };
+    if sha.result().as_slice() != header.digest {
+        return Err(ImportError::DigestMismatch);
+    }
+    Ok(res)
+}
+
+/// Reads vectors of the lengths in `g_lens`, then in `h_lens`, hashing them
+/// into `sha`.
+fn read_vectors<P1: EncodedPoint, P2: EncodedPoint, R: Read>(
+    mut inp: R,
+    g_lens: &[usize],
+    h_lens: &[usize],
+    sha: &mut Sha3_256,
+) -> Result<PointVectors<P1::Affine, P2::Affine>, ImportError> {
+    let gs = g_lens
+        .iter()
+        .map(|n| read_points_hashed::<P1, _>(&mut inp, *n, sha))
+        .collect::<Result<Vec<_>, _>>()?;
+    let hs = h_lens
+        .iter()
+        .map(|n| read_points_hashed::<P2, _>(&mut inp, *n, sha))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((gs, hs))
+}
+
 #[derive(Clone)]
 /// An updatable structured reference string for the Sonic protocol.
 pub struct USRS<E: Engine> {
@@ -50,11 +599,218 @@ impl<E: Engine> Distribution<Trapdoor<E>> for Standard {
     }
 }
 
+/// One update in a series: its `g^y` and `g^{\beta y}`, and the elements `h^x`
+/// and `h^{\alpha x}` at index `d + 1` of the SRS before and after it.
+#[derive(Clone, Copy)]
+pub struct UpdateStep<E: Engine> {
+    /// g^y, where y is the update x-permutation.
+    pub g_y: E::G1Affine,
+    /// g^{beta * y}, where beta is the update alpha-permutation.
+    pub g_by: E::G1Affine,
+    /// h^x of the previous SRS.
+    pub h_x: E::G2Affine,
+    /// h^{alpha x} of the previous SRS.
+    pub h_ax: E::G2Affine,
+    /// h^{xy} of the updated SRS.
+    pub h_xy: E::G2Affine,
+    /// h^{alpha beta xy} of the updated SRS.
+    pub h_abxy: E::G2Affine,
+}
+
+impl<E: Engine> UpdateStep<E> {
+    /// The step from `prev` to `next`.
+    fn new(
+        prev: &USRS<E>,
+        next: &USRS<E>,
+        g_y: E::G1Affine,
+        g_by: E::G1Affine,
+    ) -> Self {
+        let d = prev.d;
+        UpdateStep {
+            g_y,
+            g_by,
+            h_x: prev.h_x[d + 1],
+            h_ax: prev.h_ax[d + 1],
+            h_xy: next.h_x[d + 1],
+            h_abxy: next.h_ax[d + 1],
+        }
+    }
+}
+
+/// How an update proves knowledge of its exponents `y` and `\beta y`, and that
+/// the SRS changed by them: a NIZK, together with the links between the update
+/// and the SRS that its statement does not cover.
+///
+/// Any NIZK proving knowledge of the exponents of `(g^y, g^{\beta y})` is an
+/// update proof, with the links checked by pairings. [`Linked`] NIZKs instead
+/// prove the links as well.
+///
+/// [`Linked`]: struct.Linked.html
+pub trait UpdateProof<E: Engine> {
+    /// The NIZK proving knowledge of the exponents.
+    type NIZK: NIZK;
+
+    /// The statement proven for `step`.
+    fn statement(step: &UpdateStep<E>) -> <Self::NIZK as Relation>::X;
+    /// The witness for the exponents `y` and `\beta y`.
+    fn witness(y: E::Fr, by: E::Fr) -> <Self::NIZK as Relation>::W;
+    /// Checks the links of a single step not covered by its proof.
+    fn check_links(step: &UpdateStep<E>) -> Result<(), VerificationError>;
+    /// Checks the links of a series of steps not covered by their proofs, at
+    /// once.
+    fn check_chain<R: Rng + CryptoRng + ?Sized>(
+        steps: &[UpdateStep<E>],
+        rng: &mut R,
+    ) -> Result<(), VerificationError>;
+}
+
+/// Proves knowledge of the exponents of `g^y` and `g^{\beta y}`, and checks
+/// with pairings that the SRS changed by the same exponents.
+impl<E: Engine, N: NIZK<X = CurvePair<E::G1Affine>, W = FieldPair<E::Fr>>>
+    UpdateProof<E> for N
+{
+    type NIZK = N;
+
+    fn statement(step: &UpdateStep<E>) -> CurvePair<E::G1Affine> {
+        CurvePair::new(step.g_y, step.g_by)
+    }
+
+    fn witness(y: E::Fr, by: E::Fr) -> FieldPair<E::Fr> {
+        FieldPair::new(y, by)
+    }
+
+    fn check_links(step: &UpdateStep<E>) -> Result<(), VerificationError> {
+        let g = E::G1Affine::one();
+        let e = E::pairing;
+        check!(
+            e(step.g_by, step.h_ax) == e(g, step.h_abxy),
+            VerificationError::AlphaLink(0)
+        );
+        check!(
+            e(step.g_y, step.h_x) == e(g, step.h_xy),
+            VerificationError::XLink(0)
+        );
+        Ok(())
+    }
+
+    fn check_chain<R: Rng + CryptoRng + ?Sized>(
+        steps: &[UpdateStep<E>],
+        rng: &mut R,
+    ) -> Result<(), VerificationError> {
+        let g = E::G1Affine::one();
+        let e = E::pairing;
+        let h_xs = steps
+            .par_iter()
+            .map(|s| s.h_x)
+            .chain(steps.par_iter().map(|s| s.h_ax));
+        let h_xys = steps
+            .par_iter()
+            .map(|s| s.h_xy)
+            .chain(steps.par_iter().map(|s| s.h_abxy))
+            .collect::<Vec<_>>();
+        let g_ys = steps
+            .par_iter()
+            .map(|s| s.g_y)
+            .chain(steps.par_iter().map(|s| s.g_by));
+        let rnd = random_scalars::<E::Fr, _>(2 * steps.len(), rng);
+        // rhs = e(g, \sum_i h^{x_{i+1}r_i)
+        let rhs = E::final_exponentiation(&E::miller_loop(&[(
+            &g.prepare(),
+            &multiexp(h_xys.iter(), rnd.iter()).into_affine().prepare(),
+        )]))
+        .unwrap();
+        // lhs = \prod_i e(g^{x_i, r_i}, h^{x_i})
+        let lhs = g_ys
+            .zip(h_xs)
+            .zip(rnd)
+            .map(|((g_y, h_x), r)| e(g_y.mul(r), h_x))
+            .reduce(E::Fqk::one, |mut a, b| {
+                a.mul_assign(&b);
+                a
+            });
+        check!(lhs == rhs, VerificationError::Chain);
+        Ok(())
+    }
+}
+
+/// The statement proven by a [`Linked`] update proof: that `g^y` shares its
+/// exponent with the step from the previous to the updated `h^x`, and
+/// `g^{\beta y}` with the step in `h^{\alpha x}`.
+///
+/// [`Linked`]: struct.Linked.html
+pub type LinkStatement<E> = Composed<
+    CrossGroupStatement<<E as Engine>::G1Affine, <E as Engine>::G2Affine>,
+    CrossGroupStatement<<E as Engine>::G1Affine, <E as Engine>::G2Affine>,
+>;
+
+/// The witness `(y, \beta y)` of a [`LinkStatement`].
+///
+/// [`LinkStatement`]: type.LinkStatement.html
+pub type LinkWitness<E> = Composed<
+    FieldElement<<E as ScalarEngine>::Fr>,
+    FieldElement<<E as ScalarEngine>::Fr>,
+>;
+
+/// The sigma protocol for a [`LinkStatement`].
+///
+/// [`LinkStatement`]: type.LinkStatement.html
+pub type LinkSigmaProtocol<E> = AndComposition<
+    CrossGroupSigmaProtocol<<E as Engine>::G1Affine, <E as Engine>::G2Affine>,
+    CrossGroupSigmaProtocol<<E as Engine>::G1Affine, <E as Engine>::G2Affine>,
+>;
+
+/// The update proof whose NIZK `N` also proves the links to the SRS in G2,
+/// using [`LinkSigmaProtocol`].
+///
+/// Instead of proving knowledge of the exponents of `g^y` and `g^{\beta y}` and
+/// checking with pairings that the SRS changed by the same exponents, the proof
+/// covers both at once. Verification then needs no pairings outside the
+/// structure check, but the proof works in G2 as well. As the proof covers the
+/// links, a broken link is reported as an invalid proof rather than as
+/// [`XLink`], [`AlphaLink`] or [`Chain`].
+///
+/// [`LinkSigmaProtocol`]: type.LinkSigmaProtocol.html
+/// [`XLink`]: enum.VerificationError.html#variant.XLink
+/// [`AlphaLink`]: enum.VerificationError.html#variant.AlphaLink
+/// [`Chain`]: enum.VerificationError.html#variant.Chain
+pub struct Linked<N>(PhantomData<N>);
+
+impl<E: Engine, N: NIZK<X = LinkStatement<E>, W = LinkWitness<E>>>
+    UpdateProof<E> for Linked<N>
+{
+    type NIZK = N;
+
+    fn statement(step: &UpdateStep<E>) -> LinkStatement<E> {
+        let g = E::G1Affine::one();
+        Composed::new(
+            CrossGroupStatement::new(g, step.h_x, step.g_y, step.h_xy),
+            CrossGroupStatement::new(g, step.h_ax, step.g_by, step.h_abxy),
+        )
+    }
+
+    fn witness(y: E::Fr, by: E::Fr) -> LinkWitness<E> {
+        Composed::new(FieldElement::new(y), FieldElement::new(by))
+    }
+
+    fn check_links(_: &UpdateStep<E>) -> Result<(), VerificationError> {
+        Ok(())
+    }
+
+    fn check_chain<R: Rng + CryptoRng + ?Sized>(
+        _: &[UpdateStep<E>],
+        _: &mut R,
+    ) -> Result<(), VerificationError> {
+        Ok(())
+    }
+}
+
 /// An update proof over Sonic's reference string.
-pub struct Update<
-    E: Engine,
-    N: NIZK<X = CurvePair<E::G1Affine>, W = FieldPair<E::Fr>>,
-> {
+///
+/// The proof mode `P` is usually a NIZK proving knowledge of the exponents of
+/// `g^y` and `g^{\beta y}`; see [`UpdateProof`] for the alternatives.
+///
+/// [`UpdateProof`]: trait.UpdateProof.html
+pub struct Update<E: Engine, P: UpdateProof<E>> {
     /// The final SRS.
     srs: USRS<E>,
     /// g^y, where y is the update x-permutation.
@@ -62,94 +818,165 @@ pub struct Update<
     /// g^{beta * y}, where beta is the update alpha-permutation.
     g_by: E::G1Affine,
     /// The proof of knowledge of exponent for g^y and g^{beta * y}.
-    pi: N::Proof,
+    pi: <P::NIZK as NIZK>::Proof,
 }
 
-impl<E: Engine, N: NIZK<X = CurvePair<E::G1Affine>, W = FieldPair<E::Fr>>>
-    Update<E, N>
-{
+/// An update whose proof also links it to the SRS in G2; see [`Linked`].
+///
+/// [`Linked`]: struct.Linked.html
+pub type LinkedUpdate<E, N> = Update<E, Linked<N>>;
+
+impl<E: Engine, P: UpdateProof<E>> Update<E, P> {
     /// Creates a randomly sampled update to a SRS.
-    pub fn new<R: Split + Rng + CryptoRng>(srs: &USRS<E>, rng: &mut R) -> Self
-    where
-        R: Send,
-    {
+    pub fn new<R: Split + Rng + CryptoRng + Send>(
+        srs: &USRS<E>,
+        rng: &mut R,
+    ) -> Self {
         let trapdoor: Trapdoor<E> = rng.gen();
-        let mut tmp = E::G1Affine::one().mul(trapdoor.x);
-        let g_y = tmp.into_affine();
-        tmp.mul_assign(trapdoor.alpha);
-        let g_by = tmp.into_affine();
+        let g_y = E::G1Affine::one().mul(trapdoor.x).into_affine();
+        let g_by = g_y.mul(trapdoor.alpha).into_affine();
         let by = {
             let mut tmp = trapdoor.x;
             tmp.mul_assign(&trapdoor.alpha);
             tmp
         };
+        let next = srs.permute(&trapdoor);
+        let pi = P::NIZK::prove(
+            &P::statement(&UpdateStep::new(srs, &next, g_y, g_by)),
+            &P::witness(trapdoor.x, by),
+            rng,
+        );
         Update {
-            srs: srs.permute(&trapdoor),
-            g_y: g_y,
-            g_by: g_by,
-            pi: N::prove(
-                &CurvePair::new(g_y, g_by),
-                &FieldPair::new(trapdoor.x, by),
-                rng,
-            ),
+            srs: next,
+            g_y,
+            g_by,
+            pi,
         }
     }
 
+    /// Creates a randomly sampled update to a SRS, running in the pool chosen
+    /// by `par`.
+    ///
+    /// Contributions can run in the background this way without starving other
+    /// work.
+    pub fn new_in<R: Split + Rng + CryptoRng + Send>(
+        par: Parallelism,
+        srs: &USRS<E>,
+        rng: &mut R,
+    ) -> Self
+    where
+        Self: Send,
+    {
+        par.install(|| Self::new(srs, rng))
+    }
+
     /// Verify the SRS update.
     pub fn verify<R: Rng + CryptoRng + ?Sized>(
         &self,
         srs: &USRS<E>,
         rng: &mut R,
     ) -> bool {
-        let d = srs.d;
+        self.try_verify(srs, rng).is_ok()
+    }
+
+    /// Verify the SRS update, reporting which check failed.
+    pub fn try_verify<R: Rng + CryptoRng + ?Sized>(
+        &self,
+        srs: &USRS<E>,
+        rng: &mut R,
+    ) -> Result<(), VerificationError> {
         let g = E::G1Affine::one();
-        let e = E::pairing;
-        check!(self.g_y != g && self.g_by != g);
-        check!(self.srs.d == srs.d);
-        check!(N::verify(&CurvePair::new(self.g_y, self.g_by), &self.pi));
-        check!(e(self.g_by, srs.h_ax[d + 1]) == e(g, self.srs.h_ax[d + 1]));
-        check!(e(self.g_y, srs.h_x[d + 1]) == e(g, self.srs.h_x[d + 1]));
-        check!(self.srs.verify_structure(rng));
-        true
+        check!(
+            self.g_y != g && self.g_by != g,
+            VerificationError::TrivialUpdate(0)
+        );
+        check!(
+            self.srs.d == srs.d,
+            VerificationError::Degree {
+                expected: srs.d,
+                found: self.srs.d,
+            }
+        );
+        let step = UpdateStep::new(srs, &self.srs, self.g_y, self.g_by);
+        check!(
+            P::NIZK::verify(&P::statement(&step), &self.pi),
+            VerificationError::ProofOfExponent(0)
+        );
+        P::check_links(&step)?;
+        self.srs.try_verify_structure(rng)
     }
 }
 
-impl<E: Engine, N: NIZK<X = CurvePair<E::G1Affine>, W = FieldPair<E::Fr>>>
-    Into<USRS<E>> for Update<E, N>
+impl<E: Engine, P: UpdateProof<E>> From<Update<E, P>> for USRS<E> {
+    fn from(upd: Update<E, P>) -> Self {
+        upd.srs
+    }
+}
+
+/// Encoded as the final SRS, followed by `g_y`, `g_by` and the proof.
+impl<E: Engine, P: UpdateProof<E>> Serializable for Update<E, P>
+where
+    P::NIZK: SerializableNIZK,
 {
-    fn into(self) -> USRS<E> {
-        self.srs
+    fn write<W: Write>(&self, mut out: W) -> io::Result<()> {
+        self.srs.write(&mut out)?;
+        write_point(&self.g_y, &mut out)?;
+        write_point(&self.g_by, &mut out)?;
+        P::NIZK::write_proof(&self.pi, &mut out)
+    }
+
+    fn read<R: Read>(mut inp: R) -> Result<Self, ImportError> {
+        Ok(Update {
+            srs: USRS::read(&mut inp)?,
+            g_y: read_point(&mut inp)?,
+            g_by: read_point(&mut inp)?,
+            pi: P::NIZK::read_proof(&mut inp)?,
+        })
     }
 }
 
 /// A single element in an aggregate update
-pub struct UpdatePart<
-    E: Engine,
-    N: NIZK<X = CurvePair<E::G1Affine>, W = FieldPair<E::Fr>>,
-> {
+pub struct UpdatePart<E: Engine, P: UpdateProof<E>> {
     h_x: E::G2Affine,
     h_ax: E::G2Affine,
     g_y: E::G1Affine,
     g_by: E::G1Affine,
-    pi: N::Proof,
+    pi: <P::NIZK as NIZK>::Proof,
+}
+
+/// Encoded as `h_x`, `h_ax`, `g_y`, `g_by` and the proof.
+impl<E: Engine, P: UpdateProof<E>> Serializable for UpdatePart<E, P>
+where
+    P::NIZK: SerializableNIZK,
+{
+    fn write<W: Write>(&self, mut out: W) -> io::Result<()> {
+        write_point(&self.h_x, &mut out)?;
+        write_point(&self.h_ax, &mut out)?;
+        write_point(&self.g_y, &mut out)?;
+        write_point(&self.g_by, &mut out)?;
+        P::NIZK::write_proof(&self.pi, &mut out)
+    }
+
+    fn read<R: Read>(mut inp: R) -> Result<Self, ImportError> {
+        Ok(UpdatePart {
+            h_x: read_point(&mut inp)?,
+            h_ax: read_point(&mut inp)?,
+            g_y: read_point(&mut inp)?,
+            g_by: read_point(&mut inp)?,
+            pi: P::NIZK::read_proof(&mut inp)?,
+        })
+    }
 }
 
 /// A series of SRS updates from an initial empty SRS.
-pub struct AggregateUpdate<
-    E: Engine,
-    N: NIZK<X = CurvePair<E::G1Affine>, W = FieldPair<E::Fr>>,
-> {
+pub struct AggregateUpdate<E: Engine, P: UpdateProof<E>> {
     /// The final SRS.
     srs: USRS<E>,
     /// The series of update parts.
-    upds: Vec<UpdatePart<E, N>>,
+    upds: Vec<UpdatePart<E, P>>,
 }
 
-impl<E: Engine, N: NIZK<X = CurvePair<E::G1Affine>, W = FieldPair<E::Fr>>>
-    AggregateUpdate<E, N>
-where
-    UpdatePart<E, N>: Send + Sync,
-{
+impl<E: Engine, P: UpdateProof<E>> AggregateUpdate<E, P> {
     /// Creates an empty SRS update.
     pub fn new(d: usize) -> Self {
         AggregateUpdate {
@@ -159,7 +986,7 @@ where
     }
 
     /// Adds a new update to the end of the aggregate list.
-    pub fn append(&mut self, upd: Update<E, N>) {
+    pub fn append(&mut self, upd: Update<E, P>) {
         let nxt = UpdatePart {
             h_x: self.srs.h_x[self.srs.d + 1],
             h_ax: self.srs.h_ax[self.srs.d + 1],
@@ -172,96 +999,248 @@ where
     }
 
     /// Verifies the series of updates.
-    pub fn verify<R: Rng + CryptoRng + ?Sized>(&self, rng: &mut R) -> bool {
+    pub fn verify<R: Rng + CryptoRng + ?Sized>(&self, rng: &mut R) -> bool
+    where
+        UpdatePart<E, P>: Send + Sync,
+    {
+        self.try_verify(rng).is_ok()
+    }
+
+    /// Verifies the series of updates, running in the pool chosen by `par`.
+    pub fn verify_in<R: Rng + CryptoRng + Send + ?Sized>(
+        &self,
+        par: Parallelism,
+        rng: &mut R,
+    ) -> bool
+    where
+        UpdatePart<E, P>: Send + Sync,
+    {
+        self.try_verify_in(par, rng).is_ok()
+    }
+
+    /// Verifies the series of updates, running in the pool chosen by `par`, and
+    /// reporting which check failed.
+    pub fn try_verify_in<R: Rng + CryptoRng + Send + ?Sized>(
+        &self,
+        par: Parallelism,
+        rng: &mut R,
+    ) -> Result<(), VerificationError>
+    where
+        UpdatePart<E, P>: Send + Sync,
+    {
+        par.install(|| self.try_verify(rng))
+    }
+
+    /// Verifies the series of updates, reporting which check failed.
+    pub fn try_verify<R: Rng + CryptoRng + ?Sized>(
+        &self,
+        rng: &mut R,
+    ) -> Result<(), VerificationError>
+    where
+        UpdatePart<E, P>: Send + Sync,
+    {
+        self.try_verify_with(rng, |steps, _| {
+            match steps.par_iter().zip(self.upds.par_iter()).position_first(
+                |(s, u)| !P::NIZK::verify(&P::statement(s), &u.pi),
+            ) {
+                Some(i) => Err(VerificationError::ProofOfExponent(i)),
+                None => Ok(()),
+            }
+        })
+    }
+
+    /// Verifies the series of updates as [`verify`] does, but checking the
+    /// proofs of knowledge together with [`BatchVerify`].
+    ///
+    /// [`verify`]: #method.verify
+    /// [`BatchVerify`]: ../poe/trait.BatchVerify.html
+    pub fn verify_batched<R: Rng + CryptoRng + ?Sized>(
+        &self,
+        rng: &mut R,
+    ) -> bool
+    where
+        P::NIZK: BatchVerify,
+        <P::NIZK as Relation>::X: Send + Sync,
+        <P::NIZK as NIZK>::Proof: Sync,
+    {
+        self.try_verify_batched(rng).is_ok()
+    }
+
+    /// Verifies the series of updates as [`verify_batched`] does, running in
+    /// the pool chosen by `par`.
+    ///
+    /// [`verify_batched`]: #method.verify_batched
+    pub fn verify_batched_in<R: Rng + CryptoRng + Send + ?Sized>(
+        &self,
+        par: Parallelism,
+        rng: &mut R,
+    ) -> bool
+    where
+        P::NIZK: BatchVerify,
+        <P::NIZK as Relation>::X: Send + Sync,
+        <P::NIZK as NIZK>::Proof: Sync,
+    {
+        self.try_verify_batched_in(par, rng).is_ok()
+    }
+
+    /// Verifies the series of updates as [`verify_batched`] does, running in
+    /// the pool chosen by `par`, and reporting which check failed.
+    ///
+    /// [`verify_batched`]: #method.verify_batched
+    pub fn try_verify_batched_in<R: Rng + CryptoRng + Send + ?Sized>(
+        &self,
+        par: Parallelism,
+        rng: &mut R,
+    ) -> Result<(), VerificationError>
+    where
+        P::NIZK: BatchVerify,
+        <P::NIZK as Relation>::X: Send + Sync,
+        <P::NIZK as NIZK>::Proof: Sync,
+    {
+        par.install(|| self.try_verify_batched(rng))
+    }
+
+    /// Verifies the series of updates as [`verify_batched`] does, reporting
+    /// which check failed.
+    ///
+    /// For the Fiat-Shamir dual proof of exponent, the batched check is a
+    /// single multiexp over all updates. If it fails, the proofs are checked
+    /// one at a time to find the offending update.
+    ///
+    /// [`verify_batched`]: #method.verify_batched
+    pub fn try_verify_batched<R: Rng + CryptoRng + ?Sized>(
+        &self,
+        rng: &mut R,
+    ) -> Result<(), VerificationError>
+    where
+        P::NIZK: BatchVerify,
+        <P::NIZK as Relation>::X: Send + Sync,
+        <P::NIZK as NIZK>::Proof: Sync,
+    {
+        self.try_verify_with(rng, |steps, rng| {
+            let proofs = steps
+                .par_iter()
+                .zip(self.upds.par_iter())
+                .map(|(s, u)| (P::statement(s), &u.pi))
+                .collect::<Vec<_>>();
+            if P::NIZK::batch_verify(&proofs, rng) {
+                return Ok(());
+            }
+            // Find the offending proof only once the batch has failed.
+            Err(proofs
+                .par_iter()
+                .position_first(|(x, pi)| !P::NIZK::verify(x, pi))
+                .map_or(
+                    VerificationError::BatchProof,
+                    VerificationError::ProofOfExponent,
+                ))
+        })
+    }
+
+    /// The steps of the series of updates.
+    fn steps(&self) -> Vec<UpdateStep<E>> {
+        let d = self.srs.d;
+        let nexts = self.upds[1..].iter().map(|u| (u.h_x, u.h_ax)).chain(
+            std::iter::once((self.srs.h_x[d + 1], self.srs.h_ax[d + 1])),
+        );
+        self.upds
+            .iter()
+            .zip(nexts)
+            .map(|(u, (h_xy, h_abxy))| UpdateStep {
+                g_y: u.g_y,
+                g_by: u.g_by,
+                h_x: u.h_x,
+                h_ax: u.h_ax,
+                h_xy,
+                h_abxy,
+            })
+            .collect()
+    }
+
+    /// Verifies the series of updates, checking the proofs of knowledge of each
+    /// step with `check_proofs`.
+    fn try_verify_with<R, F>(
+        &self,
+        rng: &mut R,
+        check_proofs: F,
+    ) -> Result<(), VerificationError>
+    where
+        R: Rng + CryptoRng + ?Sized,
+        F: FnOnce(&[UpdateStep<E>], &mut R) -> Result<(), VerificationError>,
+    {
         let g = E::G1Affine::one();
         let h = E::G2Affine::one();
-        if self.upds.len() == 0 {
-            check!(self
-                .srs
-                .h_x
-                .iter()
-                .chain(self.srs.h_ax.iter())
-                .all(|h_x| h_x == &h));
-            check!(self
-                .srs
-                .g_x
-                .iter()
-                .chain(self.srs.g_ax.iter())
-                .all(|g_x| g_x == &g));
-            return true;
+        if self.upds.is_empty() {
+            check!(
+                self.srs
+                    .h_x
+                    .iter()
+                    .chain(self.srs.h_ax.iter())
+                    .all(|h_x| h_x == &h),
+                VerificationError::Initial
+            );
+            check!(
+                self.srs
+                    .g_x
+                    .iter()
+                    .chain(self.srs.g_ax.iter())
+                    .all(|g_x| g_x == &g),
+                VerificationError::Initial
+            );
+            return Ok(());
         }
-        let e = E::pairing;
-        check!(self
-            .upds
-            .par_iter()
-            .all(|u| N::verify(&CurvePair::new(u.g_y, u.g_by), &u.pi)));
-        check!(self.upds.par_iter().all(|u| u.g_y != g && u.g_by != g));
-        check!(self.upds[0].h_x == h);
-        check!(self.upds[0].h_ax == h);
-        let h_xs = self
-            .upds
-            .par_iter()
-            .map(|u| u.h_x)
-            .chain(self.upds.par_iter().map(|u| u.h_ax));
-        let h_xys = self.upds[1..]
-            .par_iter()
-            .map(|u| u.h_x)
-            .chain(once(self.srs.h_x[self.srs.d + 1]))
-            .chain(self.upds[1..].par_iter().map(|u| u.h_ax))
-            .chain(once(self.srs.h_ax[self.srs.d + 1]))
-            .collect::<Vec<_>>();
-        let g_ys = self
-            .upds
-            .par_iter()
-            .map(|u| u.g_y)
-            .chain(self.upds.par_iter().map(|u| u.g_by));
-        let rnd =
-            vec![<E as ScalarEngine>::Fr::random(rng); 2 * self.upds.len()];
-        let mut neg_g = g;
-        neg_g.negate();
-        // rhs = e(g, \sum_i h^{x_{i+1}r_i)
-        let rhs = E::final_exponentiation(&E::miller_loop(&[(
-            &g.prepare(),
-            &multiexp(h_xys.iter(), rnd.iter()).into_affine().prepare(),
-        )]))
-        .unwrap();
-        // lhs = \prod_i e(g^{x_i, r_i}, h^{x_i})
-        let lhs = g_ys
-            .zip(h_xs)
-            .zip(rnd)
-            .map(|((g_y, h_x), r)| e(g_y.mul(r), h_x))
-            .reduce(E::Fqk::one, |mut a, b| {
-                a.mul_assign(&b);
-                a
-            });
-        check!(lhs == rhs);
-        check!(self.srs.verify_structure(rng));
-        true
+        if let Some(i) =
+            self.upds.iter().position(|u| u.g_y == g || u.g_by == g)
+        {
+            return Err(VerificationError::TrivialUpdate(i));
+        }
+        let steps = self.steps();
+        check_proofs(&steps, rng)?;
+        check!(
+            self.upds[0].h_x == h && self.upds[0].h_ax == h,
+            VerificationError::Initial
+        );
+        P::check_chain(&steps, rng)?;
+        self.srs.try_verify_structure(rng)
     }
 }
 
-impl<E: Engine, N: NIZK<X = CurvePair<E::G1Affine>, W = FieldPair<E::Fr>>>
-    Into<USRS<E>> for AggregateUpdate<E, N>
-{
-    fn into(self) -> USRS<E> {
-        self.srs
+impl<E: Engine, P: UpdateProof<E>> From<AggregateUpdate<E, P>> for USRS<E> {
+    fn from(agg: AggregateUpdate<E, P>) -> Self {
+        agg.srs
     }
 }
 
-impl<E: Engine, N: NIZK<X = CurvePair<E::G1Affine>, W = FieldPair<E::Fr>>>
-    AsRef<USRS<E>> for AggregateUpdate<E, N>
-{
+impl<E: Engine, P: UpdateProof<E>> AsRef<USRS<E>> for AggregateUpdate<E, P> {
     fn as_ref(&self) -> &USRS<E> {
         &self.srs
     }
 }
 
+/// Encoded as the final SRS, followed by the list of update parts.
+impl<E: Engine, P: UpdateProof<E>> Serializable for AggregateUpdate<E, P>
+where
+    P::NIZK: SerializableNIZK,
+{
+    fn write<W: Write>(&self, mut out: W) -> io::Result<()> {
+        self.srs.write(&mut out)?;
+        self.upds.write(&mut out)
+    }
+
+    fn read<R: Read>(mut inp: R) -> Result<Self, ImportError> {
+        Ok(AggregateUpdate {
+            srs: USRS::read(&mut inp)?,
+            upds: Vec::read(&mut inp)?,
+        })
+    }
+}
+
 impl<E: Engine> USRS<E> {
     /// Initialises an empty SRS.
     pub fn new(d: usize) -> Self {
         assert!(d >= 2);
         USRS {
-            d: d,
+            d,
             g_x: vec![E::G1Affine::one(); 2 * d + 1],
             h_x: vec![E::G2Affine::one(); 2 * d + 1],
             g_ax: vec![E::G1Affine::one(); 2 * d + 1],
@@ -269,16 +1248,130 @@ impl<E: Engine> USRS<E> {
         }
     }
 
-    /// Exports the SRS to a file.
-    pub fn export<W: Write>(&self, mut out: W) -> io::Result<()> {
-        out.write_all((self.d as u64).to_le_bytes().as_ref())?;
-        for g in self.g_x.iter().chain(self.g_ax.iter()) {
-            out.write_all(g.into_compressed().as_ref())?;
+    /// The SHA3-256 digest of the SRS, as recorded by [`export`].
+    ///
+    /// This hashes the dimension as a little-endian `u64`, followed by the
+    /// compressed points of `g_x`, `g_ax`, `h_x` and `h_ax`; that is, exactly
+    /// the contents of an unversioned export.
+    ///
+    /// [`export`]: #method.export
+    pub fn digest(&self) -> [u8; 32] {
+        self.digest_with(true)
+    }
+
+    /// The digest of the SRS with compressed or uncompressed points.
+    fn digest_with(&self, compressed: bool) -> [u8; 32] {
+        points_digest::<E>(
+            self.d,
+            &[&self.g_x, &self.g_ax],
+            &[&self.h_x, &self.h_ax],
+            compressed,
+        )
+    }
+
+    /// The file header describing this SRS.
+    pub fn header(&self, compressed: bool) -> SRSHeader {
+        SRSHeader {
+            magic: SRS_MAGIC,
+            version: SRS_VERSION,
+            curve: curve_id::<E>(),
+            compressed,
+            d: self.d,
+            digest: self.digest_with(compressed),
+        }
+    }
+
+    /// Exports the SRS to a file, with compressed points.
+    ///
+    /// See [`SRSHeader`] for the file layout.
+    ///
+    /// [`SRSHeader`]: struct.SRSHeader.html
+    pub fn export<W: Write>(&self, out: W) -> io::Result<()> {
+        self.export_with(out, true)
+    }
+
+    /// Exports the SRS to a file, with uncompressed points.
+    ///
+    /// This doubles the file size, but makes importing considerably cheaper.
+    pub fn export_uncompressed<W: Write>(&self, out: W) -> io::Result<()> {
+        self.export_with(out, false)
+    }
+
+    fn export_with<W: Write>(
+        &self,
+        out: W,
+        compressed: bool,
+    ) -> io::Result<()> {
+        write_points_file::<E, _>(
+            out,
+            &self.header(compressed),
+            &[&self.g_x, &self.g_ax],
+            &[&self.h_x, &self.h_ax],
+        )
+    }
+
+    /// Assembles an SRS from the vectors read from a file.
+    fn from_vectors(
+        d: usize,
+        (gs, hs): PointVectors<E::G1Affine, E::G2Affine>,
+    ) -> Self {
+        let [g_x, g_ax]: [_; 2] =
+            gs.try_into().expect("an SRS file has two vectors in G1");
+        let [h_x, h_ax]: [_; 2] =
+            hs.try_into().expect("an SRS file has two vectors in G2");
+        USRS {
+            d,
+            g_x,
+            h_x,
+            g_ax,
+            h_ax,
         }
-        for h in self.h_x.iter().chain(self.h_ax.iter()) {
-            out.write_all(h.into_compressed().as_ref())?;
+    }
+
+    /// Imports an SRS written by [`export`] or [`export_uncompressed`].
+    ///
+    /// All group elements are decoded and checked to lie in the prime order
+    /// subgroup, and the SRS is checked against the curve and digest recorded
+    /// in its header. Unversioned files, consisting of just the dimension and
+    /// compressed points, are also accepted. The SRS structure itself is *not*
+    /// verified; see [`verify_structure`].
+    ///
+    /// [`export`]: #method.export
+    /// [`export_uncompressed`]: #method.export_uncompressed
+    /// [`verify_structure`]: #method.verify_structure
+    pub fn import<R: Read>(mut inp: R) -> Result<Self, ImportError> {
+        let mut start = [0u8; 8];
+        inp.read_exact(&mut start)?;
+        if start != SRS_MAGIC {
+            // Unversioned files start directly with the dimension.
+            let d = check_dimension(u64::from_le_bytes(start))?;
+            let n = 2 * d + 1;
+            let vectors = read_vectors::<G1Compressed<E>, G2Compressed<E>, _>(
+                inp,
+                &[n, n],
+                &[n, n],
+                &mut Sha3_256::new(),
+            )?;
+            return Ok(Self::from_vectors(d, vectors));
         }
-        Ok(())
+        let header = SRSHeader::read_after_magic(
+            &mut inp,
+            SRS_MAGIC,
+            SRS_VERSION,
+            check_dimension,
+        )?;
+        Self::import_after_header(inp, &header)
+    }
+
+    /// Imports the points of a versioned file, checking them against its
+    /// header.
+    fn import_after_header<R: Read>(
+        inp: R,
+        header: &SRSHeader,
+    ) -> Result<Self, ImportError> {
+        let n = 2 * header.d + 1;
+        let vectors = read_points_file::<E, _>(inp, header, &[n, n], &[n, n])?;
+        Ok(Self::from_vectors(header.d, vectors))
     }
 
     /// Verifies the SRS structure.
@@ -286,15 +1379,49 @@ impl<E: Engine> USRS<E> {
         &self,
         rng: &mut R,
     ) -> bool {
+        self.try_verify_structure(rng).is_ok()
+    }
+
+    /// Verifies the SRS structure, reporting which check failed.
+    pub fn try_verify_structure<R: Rng + CryptoRng + ?Sized>(
+        &self,
+        rng: &mut R,
+    ) -> Result<(), VerificationError> {
+        use VerificationError::Structure;
         let g = E::G1Affine::one();
         let h = E::G2Affine::one();
         let e = E::pairing;
-        check!(self.g_x[self.d] == g && self.h_x[self.d] == h);
+        let n = 2 * self.d + 1;
+        check!(
+            self.d >= 2
+                && self.g_x.len() == n
+                && self.h_x.len() == n
+                && self.g_ax.len() == n
+                && self.h_ax.len() == n,
+            Structure(StructureCheck::Length)
+        );
+        check!(
+            self.g_x[self.d] == g && self.h_x[self.d] == h,
+            Structure(StructureCheck::Generators)
+        );
+        check!(
+            self.g_ax[self.d] == g,
+            Structure(StructureCheck::UnsetAlpha)
+        );
         let t_ax = e(g, self.h_ax[self.d + 1]);
-        check!(t_ax == e(self.g_ax[self.d + 1], h));
-        check!(e(g, self.h_x[self.d + 1]) == e(self.g_x[self.d + 1], h));
+        check!(
+            t_ax == e(self.g_ax[self.d + 1], h),
+            Structure(StructureCheck::Alpha)
+        );
+        check!(
+            e(g, self.h_x[self.d + 1]) == e(self.g_x[self.d + 1], h),
+            Structure(StructureCheck::X)
+        );
         // constrain e(g, h)^{\alpha\beta (xy)^{-1}}
-        check!(t_ax == e(self.g_ax[self.d - 1], self.h_x[self.d + 2]));
+        check!(
+            t_ax == e(self.g_ax[self.d - 1], self.h_x[self.d + 2]),
+            Structure(StructureCheck::InverseX)
+        );
         let g0 = self.g_x[..2 * self.d]
             .iter()
             .chain(self.g_ax[..self.d - 1].iter())
@@ -313,49 +1440,47 @@ impl<E: Engine> USRS<E> {
             .iter()
             .chain(self.h_ax[1..].iter())
             .collect::<Vec<_>>();
-        let rnd0 = vec![<E as ScalarEngine>::Fr::random(rng); 4 * self.d - 2];
-        let rnd1 = vec![<E as ScalarEngine>::Fr::random(rng); 4 * self.d];
+        let rnd0 = random_scalars::<E::Fr, _>(4 * self.d - 2, rng);
+        let rnd1 = random_scalars::<E::Fr, _>(4 * self.d, rng);
         let mut neg_hx = self.h_x[self.d + 1];
         neg_hx.negate();
         let mut neg_gx = self.g_x[self.d + 1];
         neg_gx.negate();
         let table = [
             (
-                multiexp(g0.into_iter(), rnd0.iter())
-                    .into_affine()
-                    .prepare(),
+                multiexp(g0, rnd0.iter()).into_affine().prepare(),
                 neg_hx.prepare(),
             ),
             (
-                multiexp(g1.into_iter(), rnd0.iter())
-                    .into_affine()
-                    .prepare(),
+                multiexp(g1, rnd0.iter()).into_affine().prepare(),
                 E::G2Affine::one().prepare(),
             ),
             (
                 neg_gx.prepare(),
-                multiexp(h0.into_iter(), rnd1.iter())
-                    .into_affine()
-                    .prepare(),
+                multiexp(h0, rnd1.iter()).into_affine().prepare(),
             ),
             (
                 E::G1Affine::one().prepare(),
-                multiexp(h1.into_iter(), rnd1.iter())
-                    .into_affine()
-                    .prepare(),
+                multiexp(h1, rnd1.iter()).into_affine().prepare(),
             ),
         ];
-        let table_ref = table
-            .iter()
-            .map(|&(ref a, ref b)| (a, b))
-            .collect::<Vec<_>>();
+        let table_ref = table.iter().map(|(a, b)| (a, b)).collect::<Vec<_>>();
         let lp = E::miller_loop(&table_ref[..]);
-        E::final_exponentiation(&lp).unwrap() == E::Fqk::one()
+        check!(
+            E::final_exponentiation(&lp).unwrap() == E::Fqk::one(),
+            Structure(StructureCheck::Powers)
+        );
+        Ok(())
+    }
+
+    /// Apply a trapdoor permutation to this SRS, running in the pool chosen by
+    /// `par`.
+    pub fn permute_in(&self, par: Parallelism, trapdoor: &Trapdoor<E>) -> Self {
+        par.install(|| self.permute(trapdoor))
     }
 
     /// Apply a trapdoor permutation to this SRS.
     pub fn permute(&self, trapdoor: &Trapdoor<E>) -> Self {
-        let mut srs = self.clone();
         // beta y^i
         let mut byi = vec![trapdoor.alpha];
         // beta y^{-i}
@@ -389,34 +1514,339 @@ impl<E: Engine> USRS<E> {
         neg_yi.pop();
         neg_yi.extend(yi);
         yi = neg_yi;
-        // h^\alpha -> h^\alpha\beta
-        srs.h_ax[self.d] = srs.h_ax[self.d].mul(byi[0]).into_affine();
-        srs.g_x = self
-            .g_x
-            .par_iter()
-            .zip(yi.par_iter())
-            .map(|(gx, y)| gx.mul(*y).into_affine())
-            .collect();
-        srs.h_x = self
-            .h_x
-            .par_iter()
-            .zip(yi.par_iter())
-            .map(|(hx, y)| hx.mul(*y).into_affine())
-            .collect();
-        srs.g_ax = self
-            .g_ax
-            .par_iter()
-            .zip(byi.par_iter())
-            .map(|(gax, by)| gax.mul(*by).into_affine())
-            .collect();
-        srs.h_ax = self
-            .h_ax
-            .par_iter()
-            .zip(byi.par_iter())
-            .map(|(hax, by)| hax.mul(*by).into_affine())
-            .collect();
+        let mut srs = USRS {
+            d: self.d,
+            g_x: batch_exp(&self.g_x, &yi),
+            h_x: batch_exp(&self.h_x, &yi),
+            g_ax: batch_exp(&self.g_ax, &byi),
+            h_ax: batch_exp(&self.h_ax, &byi),
+        };
         // Unset g^\alpha.
         srs.g_ax[self.d] = E::G1Affine::one();
         srs
     }
 }
+
+/// Encoded as a compressed SRS file; see
+/// [`USRS::export`](struct.USRS.html#method.export).
+///
+/// Unlike [`USRS::import`](struct.USRS.html#method.import), reading accepts
+/// only this encoding, rejecting unversioned and uncompressed files with
+/// [`ImportError::NonCanonical`].
+///
+/// [`ImportError::NonCanonical`]: enum.ImportError.html#variant.NonCanonical
+impl<E: Engine> Serializable for USRS<E> {
+    fn write<W: Write>(&self, out: W) -> io::Result<()> {
+        self.export(out)
+    }
+
+    fn read<R: Read>(mut inp: R) -> Result<Self, ImportError> {
+        let mut start = [0u8; 8];
+        inp.read_exact(&mut start)?;
+        if start != SRS_MAGIC {
+            return Err(ImportError::NonCanonical);
+        }
+        let header = SRSHeader::read_after_magic(
+            &mut inp,
+            SRS_MAGIC,
+            SRS_VERSION,
+            check_dimension,
+        )?;
+        if !header.compressed {
+            return Err(ImportError::NonCanonical);
+        }
+        USRS::import_after_header(inp, &header)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poe::{
+        DualProofOfExponentSigmaProtocol, FiatShamirTransform,
+        FischlinTransform,
+    };
+    use crate::ro::RO;
+    use pairing::bls12_381::{Bls12, Fr, G1Affine, G2Affine};
+
+    type FiatShamir = FiatShamirTransform<
+        DualProofOfExponentSigmaProtocol<G1Affine>,
+        Sha3_256,
+    >;
+    type Fischlin =
+        FischlinTransform<DualProofOfExponentSigmaProtocol<G1Affine>, Sha3_256>;
+    type LinkedFiatShamir =
+        FiatShamirTransform<LinkSigmaProtocol<Bls12>, Sha3_256>;
+
+    fn srs() -> USRS<Bls12> {
+        let mut rng = Sha3_256::query(b"usrs test").into_rng();
+        USRS::new(2).permute(&rng.gen())
+    }
+
+    fn encode<T: Serializable>(t: &T) -> Vec<u8> {
+        let mut buf = Vec::new();
+        t.write(&mut buf).unwrap();
+        buf
+    }
+
+    /// Checks that `t` survives an encoding round trip, returning the decoded
+    /// value.
+    fn round_trip<T: Serializable>(t: &T) -> T {
+        let buf = encode(t);
+        let res = T::read(&buf[..]).unwrap();
+        assert_eq!(encode(&res), buf);
+        res
+    }
+
+    fn update_round_trip<P>()
+    where
+        P: UpdateProof<Bls12>,
+        P::NIZK: SerializableNIZK + BatchVerify,
+        <P::NIZK as Relation>::X: Send + Sync,
+        <P::NIZK as NIZK>::Proof: Send + Sync,
+    {
+        let mut rng = Sha3_256::query(b"update round trip").into_rng();
+        let mut agg = AggregateUpdate::<Bls12, P>::new(2);
+        for _ in 0..2 {
+            let upd = Update::<Bls12, P>::new(agg.as_ref(), &mut rng);
+            let read = round_trip(&upd);
+            assert!(read.verify(agg.as_ref(), &mut rng));
+            agg.append(read);
+        }
+        let read = round_trip(&agg);
+        assert!(read.verify(&mut rng));
+        assert!(read.verify_batched(&mut rng));
+    }
+
+    /// The sizes derived in the `update-size` notes.
+    #[test]
+    fn update_sizes() {
+        let mut rng = Sha3_256::query(b"update sizes").into_rng();
+        let srs = USRS::<Bls12>::new(2);
+        let srs_len = encode(&srs).len();
+        assert_eq!(srs_len, 576 * 2 + 347);
+        let upd = Update::<Bls12, Fischlin>::new(&srs, &mut rng);
+        assert_eq!(encode(&upd).len(), srs_len + 2040);
+        let mut agg = AggregateUpdate::<Bls12, Fischlin>::new(2);
+        agg.append(upd);
+        assert_eq!(encode(&agg).len(), srs_len + 8 + 2232);
+    }
+
+    /// Checks that `srs` fails verification with `check`, both in memory and
+    /// streamed.
+    fn assert_malformed(srs: &USRS<Bls12>, check: StructureCheck) {
+        let mut rng = Sha3_256::query(b"malformed srs").into_rng();
+        let err = VerificationError::Structure(check);
+        assert_eq!(srs.try_verify_structure(&mut rng), Err(err));
+        let buf = encode(srs);
+        match crate::stream::verify_structure::<Bls12, _, _>(
+            &buf[..],
+            3,
+            &mut rng,
+        ) {
+            Err(crate::stream::StreamError::Verification(e)) => {
+                assert_eq!(e, err)
+            }
+            _ => panic!("streamed verification accepted a malformed SRS"),
+        }
+    }
+
+    fn add(p: &mut G1Affine, q: G1Affine) {
+        let mut sum = p.into_projective();
+        sum.add_assign_mixed(&q);
+        *p = sum.into_affine();
+    }
+
+    #[test]
+    fn structure_rejects_single_corrupted_element() {
+        let mut rng = Sha3_256::query(b"corrupted srs").into_rng();
+        let srs = srs();
+        assert!(srs.verify_structure(&mut rng));
+        let mut bad = srs.clone();
+        add(&mut bad.g_x[0], G1Affine::one());
+        assert_malformed(&bad, StructureCheck::Powers);
+        let mut bad = srs.clone();
+        bad.h_ax[0] = bad.h_ax[1];
+        assert_malformed(&bad, StructureCheck::Powers);
+        let mut bad = srs.clone();
+        bad.g_ax[bad.d] = bad.g_x[bad.d + 1];
+        assert_malformed(&bad, StructureCheck::UnsetAlpha);
+    }
+
+    /// With a single coefficient shared by all terms, errors in two elements
+    /// that cancel out would go unnoticed.
+    #[test]
+    fn structure_rejects_cancelling_corruptions() {
+        let mut rng = Sha3_256::query(b"cancelling srs").into_rng();
+        let mut bad = USRS::<Bls12>::new(3).permute(&rng.gen());
+        let mut neg_g = G1Affine::one();
+        neg_g.negate();
+        add(&mut bad.g_x[1], G1Affine::one());
+        add(&mut bad.g_x[5], neg_g);
+        assert_malformed(&bad, StructureCheck::Powers);
+    }
+
+    #[test]
+    fn update_round_trip_fiat_shamir() {
+        update_round_trip::<FiatShamir>();
+    }
+
+    #[test]
+    fn update_round_trip_fischlin() {
+        update_round_trip::<Fischlin>();
+    }
+
+    #[test]
+    fn update_round_trip_linked() {
+        update_round_trip::<Linked<LinkedFiatShamir>>();
+    }
+
+    #[test]
+    fn linked_aggregate_rejects_broken_link() {
+        let mut rng = Sha3_256::query(b"linked aggregate").into_rng();
+        let mut agg =
+            AggregateUpdate::<Bls12, Linked<LinkedFiatShamir>>::new(2);
+        for _ in 0..2 {
+            agg.append(Update::new(agg.as_ref(), &mut rng));
+        }
+        assert_eq!(agg.try_verify(&mut rng), Ok(()));
+        assert_eq!(agg.try_verify_batched(&mut rng), Ok(()));
+        // Replacing the middle SRS breaks the link of both updates around it.
+        agg.upds[1].h_x =
+            G2Affine::one().mul(Fr::random(&mut rng)).into_affine();
+        let err = Err(VerificationError::ProofOfExponent(0));
+        assert_eq!(agg.try_verify(&mut rng), err);
+        assert_eq!(agg.try_verify_batched(&mut rng), err);
+    }
+
+    #[test]
+    fn read_accepts_only_canonical_encoding() {
+        let srs = srs();
+        let canonical = encode(&srs);
+        assert_eq!(round_trip(&srs).digest(), srs.digest());
+        let mut uncompressed = Vec::new();
+        srs.export_uncompressed(&mut uncompressed).unwrap();
+        let mut unversioned = (srs.d as u64).to_le_bytes().to_vec();
+        unversioned.extend_from_slice(&canonical[SRSHeader::LEN..]);
+        for buf in [uncompressed, unversioned].iter() {
+            assert!(USRS::<Bls12>::import(&buf[..]).is_ok());
+            assert!(matches!(
+                USRS::<Bls12>::read(&buf[..]),
+                Err(ImportError::NonCanonical)
+            ));
+        }
+    }
+
+    #[test]
+    fn import_checks_stored_digest() {
+        let srs = srs();
+        for compressed in [true, false].iter() {
+            let mut buf = Vec::new();
+            if *compressed {
+                srs.export(&mut buf).unwrap();
+            } else {
+                srs.export_uncompressed(&mut buf).unwrap();
+            }
+            let header = SRSHeader::read(&buf[..]).unwrap();
+            assert_eq!(header, srs.header(*compressed));
+            let imported = USRS::<Bls12>::import(&buf[..]).unwrap();
+            assert_eq!(imported.digest(), srs.digest());
+            // Flip a bit of the recorded digest.
+            buf[SRSHeader::LEN - 1] ^= 1;
+            assert!(matches!(
+                USRS::<Bls12>::import(&buf[..]),
+                Err(ImportError::DigestMismatch)
+            ));
+        }
+    }
+
+    #[test]
+    fn import_rejects_malformed_files() {
+        let srs = srs();
+        let mut versioned = Vec::new();
+        srs.export(&mut versioned).unwrap();
+        let mut unversioned = (srs.d as u64).to_le_bytes().to_vec();
+        unversioned.extend_from_slice(&versioned[SRSHeader::LEN..]);
+        for (buf, start) in
+            [(versioned, SRSHeader::LEN), (unversioned, 8)].iter()
+        {
+            assert!(matches!(
+                USRS::<Bls12>::import(&buf[..buf.len() - 1]),
+                Err(ImportError::Truncated)
+            ));
+            // A compressed x-coordinate above the field modulus.
+            let mut bad = buf.clone();
+            bad[*start] = 0x9f;
+            for b in bad[*start + 1..*start + 48].iter_mut() {
+                *b = 0xff;
+            }
+            assert!(matches!(
+                USRS::<Bls12>::import(&bad[..]),
+                Err(ImportError::InvalidEncoding(_))
+            ));
+        }
+        for d in [0u64, 1, u64::MAX].iter() {
+            assert!(matches!(
+                USRS::<Bls12>::import(&d.to_le_bytes()[..]),
+                Err(ImportError::InvalidLength(found)) if found == *d
+            ));
+        }
+    }
+
+    /// Exports `srs`, changes its header with `f`, and imports it again.
+    fn import_with_header(
+        srs: &USRS<Bls12>,
+        f: impl FnOnce(&mut [u8]),
+    ) -> Result<USRS<Bls12>, ImportError> {
+        let mut buf = Vec::new();
+        srs.export(&mut buf).unwrap();
+        f(&mut buf[..SRSHeader::LEN]);
+        SRSHeader::read(&buf[..])?;
+        USRS::import(&buf[..])
+    }
+
+    #[test]
+    fn header_rejects_bad_magic() {
+        // Any other start is read as the dimension of an unversioned file.
+        let mut buf = Vec::new();
+        srs().export(&mut buf).unwrap();
+        buf[0] ^= 1;
+        assert!(matches!(
+            SRSHeader::read(&buf[..]),
+            Err(ImportError::BadMagic)
+        ));
+    }
+
+    #[test]
+    fn header_rejects_unsupported_version() {
+        let res = import_with_header(&srs(), |h| {
+            h[8..10].copy_from_slice(&2u16.to_le_bytes())
+        });
+        assert!(matches!(res, Err(ImportError::UnsupportedVersion(2))));
+    }
+
+    #[test]
+    fn header_rejects_other_curves() {
+        let res = import_with_header(&srs(), |h| h[10] ^= 1);
+        assert!(matches!(res, Err(ImportError::CurveMismatch)));
+    }
+
+    #[test]
+    fn header_rejects_unsupported_flags() {
+        let res = import_with_header(&srs(), |h| h[18] |= 0x02);
+        assert!(matches!(res, Err(ImportError::UnsupportedFlags(0x03))));
+    }
+
+    #[test]
+    fn header_rejects_out_of_range_dimension() {
+        for d in [0u64, 1, u64::MAX].iter() {
+            let res = import_with_header(&srs(), |h| {
+                h[19..27].copy_from_slice(&d.to_le_bytes())
+            });
+            assert!(matches!(
+                res,
+                Err(ImportError::InvalidLength(found)) if found == *d
+            ));
+        }
+    }
+}
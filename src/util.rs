@@ -1,83 +1,159 @@
-use ff::{PrimeField, PrimeFieldRepr, ScalarEngine};
-use group::{CurveAffine, CurveProjective};
+use ff::{Field, PrimeField};
+use group::{CurveAffine, CurveProjective, Wnaf};
+use rand_core::RngCore;
 use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
 
-/// Split into independant copies. Differs from `Clone` in that the copies should act *not* be
-/// equal.
+/// Split into independant copies. Differs from `Clone` in that the copies
+/// should act *not* be equal.
 pub trait Split {
     fn split(&mut self) -> Self;
 }
 
-// Sourced from https://github.com/ebfull/sonic. Licensed under MIT
-// Adapted to introduce parallelism.
-pub(crate) fn multiexp<
-    'a,
-    G: CurveAffine,
-    IB: IntoIterator<Item = &'a G>,
-    IS: IntoIterator<Item = &'a G::Scalar>,
->(
-    g: IB,
-    s: IS,
-) -> G::Projective
-where
-    IB::IntoIter: ExactSizeIterator + Clone + Send,
-    IS::IntoIter: ExactSizeIterator + Clone + Send,
-{
-    let g = g.into_iter();
-    let s = s.into_iter();
-    assert_eq!(g.len(), s.len());
-
-    let c = if s.len() < 32 {
-        3u32
-    } else {
-        (f64::from(s.len() as u32)).ln().ceil() as u32
-    };
-
-    // Convert all of the scalars into representations
-    let s = s.map(|s| s.into_repr()).collect::<Vec<_>>();
+/// Where parallel work runs.
+///
+/// Everything in this crate parallelises through rayon, so running an operation
+/// with [`install`] confines all of its work, including any nested parallelism,
+/// to the chosen pool. The `_in` variants of the expensive operations, such as
+/// [`USRS::permute_in`], do exactly this.
+///
+/// [`install`]: #method.install
+/// [`USRS::permute_in`]: ../usrs/struct.USRS.html#method.permute_in
+#[derive(Clone, Copy, Debug, Default)]
+pub enum Parallelism<'a> {
+    /// The pool of the calling context, normally rayon's global pool.
+    #[default]
+    Global,
+    /// The given thread pool.
+    Pool(&'a ThreadPool),
+    /// A single worker thread, so that at most one core is busy. Each call gets
+    /// its own thread, so concurrent sequential operations do not wait for one
+    /// another.
+    Sequential,
+}
 
-    let mask = (1u64 << c) - 1u64;
-    let max_iters = (<G::Engine as ScalarEngine>::Fr::NUM_BITS as f64
-        / c as f64)
-        .ceil() as usize;
-    let windows = (0..max_iters)
-        .map(|i| (i, g.clone(), s.clone()))
-        .collect::<Vec<_>>()
-        .into_par_iter()
-        .map(|(cur, g, s)| {
-            let mut acc = G::Projective::zero();
-            let mut buckets = Vec::new();
+impl Parallelism<'_> {
+    /// Runs `op` in the chosen pool, blocking until it completes.
+    pub fn install<OP: FnOnce() -> R + Send, R: Send>(self, op: OP) -> R {
+        match self {
+            Parallelism::Global => op(),
+            Parallelism::Pool(pool) => pool.install(op),
+            Parallelism::Sequential => ThreadPoolBuilder::new()
+                .num_threads(1)
+                .build()
+                .expect("failed to start a single-threaded pool")
+                .install(op),
+        }
+    }
+}
 
-            buckets.truncate(0);
-            buckets.resize((1 << c) - 1, G::Projective::zero());
+/// Samples `n` independent random scalars, e.g. for the coefficients of a
+/// batched check.
+///
+/// Batched checks must not reuse one coefficient across terms: with equal
+/// coefficients, errors in different terms can cancel each other out.
+pub(crate) fn random_scalars<F: Field, R: RngCore + ?Sized>(
+    n: usize,
+    rng: &mut R,
+) -> Vec<F> {
+    (0..n).map(|_| F::random(rng)).collect()
+}
 
-            for (&(mut s), g) in s.iter().zip(g) {
-                s.shr(cur as u32 * c);
-                let index = (s.as_ref()[0] & mask) as usize;
+/// Samples `n` independent random 128-bit scalars, for the coefficients of a
+/// small-exponent batch check.
+///
+/// A batch of checks with such coefficients wrongly accepts with probability at
+/// most `2^{-128}`, matching the security level of the curves, and terms scaled
+/// only by a coefficient are cheaper than with full-size coefficients.
+pub(crate) fn random_small_scalars<F: PrimeField, R: RngCore + ?Sized>(
+    n: usize,
+    rng: &mut R,
+) -> Vec<F> {
+    (0..n)
+        .map(|_| {
+            let mut repr = F::Repr::default();
+            repr.as_mut()[0] = rng.next_u64();
+            repr.as_mut()[1] = rng.next_u64();
+            F::from_repr(repr).expect("128-bit values are below the modulus")
+        })
+        .collect()
+}
 
-                if index != 0 {
-                    buckets[index - 1].add_assign_mixed(g);
-                }
-            }
+/// The number of points normalized with a single inversion in
+/// [`batch_into_affine`].
+///
+/// [`batch_into_affine`]: fn.batch_into_affine.html
+const NORMALIZATION_CHUNK: usize = 1 << 10;
 
-            let mut running_sum = G::Projective::zero();
-            for exp in buckets.iter().rev() {
-                running_sum.add_assign(exp);
-                acc.add_assign(&running_sum);
-            }
-            acc
-        })
-        .collect::<Vec<_>>();
+/// Computes `bases[i]^{scalars[i]}` for each `i`.
+///
+/// If all bases are equal, as in a fresh SRS, they share one precomputed window
+/// table; otherwise each exponentiation uses its own wNAF window. The results
+/// are converted to affine with [`batch_into_affine`].
+pub(crate) fn batch_exp<G: CurveAffine>(
+    bases: &[G],
+    scalars: &[G::Scalar],
+) -> Vec<G> {
+    assert_eq!(bases.len(), scalars.len());
+    let res = if !bases.is_empty() && bases.iter().all(|g| *g == bases[0]) {
+        let mut wnaf = Wnaf::new();
+        let table = wnaf.base(bases[0].into_projective(), scalars.len());
+        scalars
+            .par_iter()
+            .map_init(|| table.shared(), |w, s| w.scalar(s.into_repr()))
+            .collect::<Vec<G::Projective>>()
+    } else {
+        bases
+            .par_iter()
+            .zip(scalars.par_iter())
+            .map_init(Wnaf::new, |w, (g, s)| {
+                w.scalar(s.into_repr()).base(g.into_projective())
+            })
+            .collect::<Vec<G::Projective>>()
+    };
+    batch_into_affine(res)
+}
 
-    let mut acc = G::Projective::zero();
+/// Converts projective points to affine, in batches sharing a single inversion
+/// rather than inverting for every point.
+pub(crate) fn batch_into_affine<G: CurveProjective>(
+    mut points: Vec<G>,
+) -> Vec<G::Affine> {
+    points
+        .par_chunks_mut(NORMALIZATION_CHUNK)
+        .for_each(G::batch_normalization);
+    points.par_iter().map(|g| g.into_affine()).collect()
+}
 
-    for window in windows.into_iter().rev() {
-        for _ in 0..c {
-            acc.double();
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+    use std::time::{Duration, Instant};
 
-        acc.add_assign(&window);
+    #[test]
+    fn sequential_calls_run_concurrently() {
+        let running = AtomicUsize::new(0);
+        // Each call waits, for a while, until both are running at once.
+        let overlaps = || {
+            Parallelism::Sequential.install(|| {
+                assert_eq!(rayon::current_num_threads(), 1);
+                running.fetch_add(1, Ordering::SeqCst);
+                let start = Instant::now();
+                while running.load(Ordering::SeqCst) < 2
+                    && start.elapsed() < Duration::from_secs(10)
+                {
+                    thread::yield_now();
+                }
+                running.load(Ordering::SeqCst) == 2
+            })
+        };
+        let (a, b) = thread::scope(|scope| {
+            let a = scope.spawn(overlaps);
+            let b = scope.spawn(overlaps);
+            (a.join().unwrap(), b.join().unwrap())
+        });
+        assert!(a && b);
     }
-
-    acc
 }
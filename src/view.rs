@@ -0,0 +1,352 @@
+//! A memory-mapped view of an exported SRS, decoding points lazily on first
+//! access.
+//!
+//! Opening a view only reads the header, so services can start using even very
+//! large reference strings immediately. Points are decoded a block at a time as
+//! they are needed, and cached. Uncompressed exports are considerably cheaper
+//! to decode; see [`USRS::export_uncompressed`].
+//!
+//! [`USRS::export_uncompressed`]: ../usrs/struct.USRS.html#method.export_uncompressed
+
+use crate::pc::{CommitmentError, CommitmentKey};
+use crate::stream::{self, StreamError, DEFAULT_CHUNK_SIZE};
+use crate::usrs::{curve_id, ImportError, SRSHeader, USRS};
+use crate::util::Parallelism;
+use group::{CurveAffine, EncodedPoint};
+use memmap2::Mmap;
+use pairing::Engine;
+use rand::{CryptoRng, Rng};
+use rayon::prelude::*;
+use std::fs::File;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// The number of points decoded together.
+const BLOCK_SIZE: usize = 1 << 10;
+
+/// One vector of a mapped SRS, with its decoded blocks.
+struct LazyVector<G: CurveAffine> {
+    /// The byte offset of the first point in the file.
+    offset: usize,
+    /// The number of points.
+    len: usize,
+    compressed: bool,
+    blocks: Vec<OnceLock<Vec<G>>>,
+}
+
+impl<G: CurveAffine> LazyVector<G> {
+    /// The size of an encoded point.
+    fn point_size(compressed: bool) -> usize {
+        if compressed {
+            G::Compressed::size()
+        } else {
+            G::Uncompressed::size()
+        }
+    }
+
+    fn new(offset: usize, len: usize, compressed: bool) -> Self {
+        let n_blocks = len.div_ceil(BLOCK_SIZE);
+        LazyVector {
+            offset,
+            len,
+            compressed,
+            blocks: (0..n_blocks).map(|_| OnceLock::new()).collect(),
+        }
+    }
+
+    /// The byte offset just past the last point.
+    fn end(&self) -> usize {
+        self.offset + self.len * Self::point_size(self.compressed)
+    }
+
+    /// Decodes the points from `bytes`, in parallel in the pool chosen by
+    /// `par`.
+    fn decode<P: EncodedPoint<Affine = G>>(
+        par: Parallelism,
+        bytes: &[u8],
+    ) -> Result<Vec<G>, ImportError> {
+        par.install(|| {
+            bytes
+                .par_chunks(P::size())
+                .map(|b| {
+                    let mut p = P::empty();
+                    p.as_mut().copy_from_slice(b);
+                    p.into_affine().map_err(ImportError::from)
+                })
+                .collect()
+        })
+    }
+
+    /// Returns the point at index `i`, decoding its block if necessary.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is out of bounds.
+    fn get(
+        &self,
+        par: Parallelism,
+        map: &[u8],
+        i: usize,
+    ) -> Result<G, ImportError> {
+        assert!(i < self.len, "SRS index out of bounds");
+        let block = &self.blocks[i / BLOCK_SIZE];
+        if let Some(points) = block.get() {
+            return Ok(points[i % BLOCK_SIZE]);
+        }
+        let size = Self::point_size(self.compressed);
+        let start = i / BLOCK_SIZE * BLOCK_SIZE;
+        let end = self.len.min(start + BLOCK_SIZE);
+        let bytes = &map[self.offset + start * size..self.offset + end * size];
+        let points = if self.compressed {
+            Self::decode::<G::Compressed>(par, bytes)?
+        } else {
+            Self::decode::<G::Uncompressed>(par, bytes)?
+        };
+        // Another thread may have decoded the same block meanwhile; the result
+        // is identical.
+        Ok(block.get_or_init(|| points)[i % BLOCK_SIZE])
+    }
+}
+
+/// A memory-mapped view of an SRS file written by [`USRS::export`] or
+/// [`USRS::export_uncompressed`].
+///
+/// Points are checked to lie in the prime order subgroup as they are decoded,
+/// but neither the digest nor the structure of the SRS is checked until
+/// [`verify_structure`] is called. The [`CommitmentKey`] accessors report a
+/// point that fails to decode as [`CommitmentError::InvalidPoint`].
+///
+/// [`USRS::export`]: ../usrs/struct.USRS.html#method.export
+/// [`USRS::export_uncompressed`]: ../usrs/struct.USRS.html#method.export_uncompressed
+/// [`verify_structure`]: #method.verify_structure
+/// [`CommitmentKey`]: ../pc/trait.CommitmentKey.html
+/// [`CommitmentError::InvalidPoint`]: ../pc/enum.CommitmentError.html#variant.InvalidPoint
+pub struct USRSView<'p, E: Engine> {
+    header: SRSHeader,
+    /// The pool points are decoded in.
+    par: Parallelism<'p>,
+    map: Mmap,
+    g_x: LazyVector<E::G1Affine>,
+    g_ax: LazyVector<E::G1Affine>,
+    h_x: LazyVector<E::G2Affine>,
+    h_ax: LazyVector<E::G2Affine>,
+}
+
+impl<E: Engine> USRSView<'static, E> {
+    /// Maps the SRS file at `path`.
+    ///
+    /// The file must not be modified while the view exists.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, ImportError> {
+        USRSView::open_in(Parallelism::Global, path)
+    }
+}
+
+impl<'p, E: Engine> USRSView<'p, E> {
+    /// Maps the SRS file at `path`, decoding points in the pool chosen by
+    /// `par`.
+    ///
+    /// The file must not be modified while the view exists.
+    pub fn open_in<P: AsRef<Path>>(
+        par: Parallelism<'p>,
+        path: P,
+    ) -> Result<Self, ImportError> {
+        let file = File::open(path)?;
+        // Safety: the file is only read, and the caller ensures it is not
+        // modified.
+        let map = unsafe { Mmap::map(&file)? };
+        let header = SRSHeader::read(&map[..])?;
+        if header.curve != curve_id::<E>() {
+            return Err(ImportError::CurveMismatch);
+        }
+        let (d, c) = (header.d, header.compressed);
+        let n = 2 * d + 1;
+        // Check the length before allocating anything for a possibly bogus
+        // dimension.
+        let len = (LazyVector::<E::G1Affine>::point_size(c)
+            + LazyVector::<E::G2Affine>::point_size(c))
+        .checked_mul(2 * n)
+        .and_then(|l| l.checked_add(SRSHeader::LEN))
+        .ok_or(ImportError::InvalidLength(d as u64))?;
+        if map.len() < len {
+            return Err(ImportError::Truncated);
+        } else if map.len() > len {
+            return Err(ImportError::InvalidLength(d as u64));
+        }
+        let g_x = LazyVector::new(SRSHeader::LEN, n, c);
+        let g_ax = LazyVector::new(g_x.end(), n, c);
+        let h_x = LazyVector::new(g_ax.end(), n, c);
+        let h_ax = LazyVector::new(h_x.end(), n, c);
+        Ok(USRSView {
+            header,
+            par,
+            map,
+            g_x,
+            g_ax,
+            h_x,
+            h_ax,
+        })
+    }
+
+    /// The header of the mapped file.
+    pub fn header(&self) -> &SRSHeader {
+        &self.header
+    }
+
+    /// g^{x^i} at index i + d
+    pub fn try_g_x(&self, index: usize) -> Result<E::G1Affine, ImportError> {
+        self.g_x.get(self.par, &self.map, index)
+    }
+
+    /// g^{x^i \alpha} at index i + d; At i=0, the value is undefined.
+    pub fn try_g_ax(&self, index: usize) -> Result<E::G1Affine, ImportError> {
+        self.g_ax.get(self.par, &self.map, index)
+    }
+
+    /// h^{x^i} at index i + d
+    pub fn try_h_x(&self, index: usize) -> Result<E::G2Affine, ImportError> {
+        self.h_x.get(self.par, &self.map, index)
+    }
+
+    /// h^{x^i \alpha} at index i + d
+    pub fn try_h_ax(&self, index: usize) -> Result<E::G2Affine, ImportError> {
+        self.h_ax.get(self.par, &self.map, index)
+    }
+
+    /// Decodes the entire SRS, as [`USRS::import`] would.
+    ///
+    /// [`USRS::import`]: ../usrs/struct.USRS.html#method.import
+    pub fn to_usrs(&self) -> Result<USRS<E>, ImportError> {
+        self.par.install(|| USRS::import(&self.map[..]))
+    }
+
+    /// Verifies the SRS structure, and the digest recorded in the header.
+    pub fn verify_structure<R: Rng + CryptoRng + ?Sized>(
+        &self,
+        rng: &mut R,
+    ) -> bool {
+        self.try_verify_structure(rng).is_ok()
+    }
+
+    /// Verifies the SRS structure, and the digest recorded in the header,
+    /// reporting which check failed.
+    ///
+    /// This streams through the file without filling the cache, so it does not
+    /// use more memory than a chunk of [`DEFAULT_CHUNK_SIZE`] points.
+    ///
+    /// [`DEFAULT_CHUNK_SIZE`]: ../stream/constant.DEFAULT_CHUNK_SIZE.html
+    pub fn try_verify_structure<R: Rng + CryptoRng + ?Sized>(
+        &self,
+        rng: &mut R,
+    ) -> Result<(), StreamError> {
+        stream::verify_structure::<E, _, _>(
+            &self.map[..],
+            DEFAULT_CHUNK_SIZE,
+            rng,
+        )
+    }
+}
+
+impl<E: Engine> CommitmentKey<E> for USRSView<'_, E> {
+    fn dimension(&self) -> usize {
+        self.header.d
+    }
+
+    fn g_x(&self, index: usize) -> Result<E::G1Affine, CommitmentError> {
+        self.try_g_x(index)
+            .map_err(|_| CommitmentError::InvalidPoint)
+    }
+
+    fn g_ax(&self, index: usize) -> Result<E::G1Affine, CommitmentError> {
+        self.try_g_ax(index)
+            .map_err(|_| CommitmentError::InvalidPoint)
+    }
+
+    fn h_x(&self, index: usize) -> Result<E::G2Affine, CommitmentError> {
+        self.try_h_x(index)
+            .map_err(|_| CommitmentError::InvalidPoint)
+    }
+
+    fn h_ax(&self, index: usize) -> Result<E::G2Affine, CommitmentError> {
+        self.try_h_ax(index)
+            .map_err(|_| CommitmentError::InvalidPoint)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pc::LaurentPolynomial;
+    use crate::ro::RO;
+    use ff::Field;
+    use pairing::bls12_381::{Bls12, Fr};
+    use sha3::Sha3_256;
+    use std::fs;
+    use std::path::PathBuf;
+
+    /// Writes `bytes` to a fresh file in the temporary directory.
+    fn temp_file(name: &str, bytes: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "pistis-view-{}-{}",
+            std::process::id(),
+            name
+        ));
+        fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    #[test]
+    fn view_reports_invalid_points() {
+        let mut rng = Sha3_256::query(b"view test").into_rng();
+        let srs = USRS::<Bls12>::new(2).permute(&rng.gen());
+        let mut bytes = Vec::new();
+        srs.export_uncompressed(&mut bytes).unwrap();
+        let path = temp_file("valid", &bytes);
+        let view = USRSView::<Bls12>::open(&path).unwrap();
+        let f = LaurentPolynomial::new(0, vec![Fr::one(); 2]);
+        let z = Fr::random(&mut rng);
+        let c = CommitmentKey::commit(&view, 1, &f).unwrap();
+        assert_eq!(c, srs.commit(1, &f).unwrap());
+        let opening = CommitmentKey::open(&view, &f, z).unwrap();
+        assert!(CommitmentKey::verify_opening(&view, 1, &c, z, &opening));
+
+        // Corrupting one point invalidates the block decoded with it.
+        let corrupt = |offset: usize, name: &str| {
+            let mut bytes = bytes.clone();
+            bytes[offset + 1] ^= 1;
+            let path = temp_file(name, &bytes);
+            let view = USRSView::<Bls12>::open(&path).unwrap();
+            fs::remove_file(path).unwrap();
+            view
+        };
+        let bad_g = corrupt(view.g_ax.offset, "g_ax");
+        assert_eq!(
+            CommitmentKey::commit(&bad_g, 1, &f),
+            Err(CommitmentError::InvalidPoint)
+        );
+        let bad_h = corrupt(view.h_ax.offset, "h_ax");
+        assert!(!CommitmentKey::verify_opening(&bad_h, 1, &c, z, &opening));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn view_decodes_in_chosen_pool() {
+        let mut rng = Sha3_256::query(b"view pool").into_rng();
+        let srs = USRS::<Bls12>::new(2).permute(&rng.gen());
+        let mut bytes = Vec::new();
+        srs.export(&mut bytes).unwrap();
+        let path = temp_file("pool", &bytes);
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(2)
+            .build()
+            .unwrap();
+        for par in [Parallelism::Sequential, Parallelism::Pool(&pool)].iter() {
+            let view = USRSView::<Bls12>::open_in(*par, &path).unwrap();
+            for i in 0..5 {
+                assert_eq!(view.try_g_x(i).unwrap(), srs.g_x[i]);
+                assert_eq!(view.try_h_ax(i).unwrap(), srs.h_ax[i]);
+            }
+            assert_eq!(view.to_usrs().unwrap().digest(), srs.digest());
+        }
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,533 @@
+//! Interoperability with the "powers of tau" ceremony file layout used by Zcash
+//! for BLS12-381.
+//!
+//! A ceremony accumulator contains `g^{\tau^i}` for `i` from 0 to `2n - 2`, and
+//! `h^{\tau^i}`, `g^{\alpha \tau^i}` and `g^{\beta \tau^i}` for `i` from 0 to
+//! `n - 1`, along with `h^\beta`. With `x = \tau`, this covers the positive
+//! powers of `g_x` and `h_x`, and the positive powers of `g_ax`, of a [`USRS`].
+//! It contains none of the negative powers, nor `h_ax`, so an accumulator alone
+//! can never yield a full [`USRS`]; conversions go through [`PartialUSRS`],
+//! which records exactly which elements are known.
+//!
+//! Conversely, a [`USRS`] lacks `g^\alpha` and all of the `\beta` elements.
+//! These are written as the point at infinity, which ceremony tooling rejects,
+//! so exported files cannot be mistaken for real contributions.
+//!
+//! [`USRS`]: ../usrs/struct.USRS.html
+//! [`PartialUSRS`]: struct.PartialUSRS.html
+
+use crate::usrs::{read_points, ImportError, USRS};
+use group::{CurveAffine, EncodedPoint};
+use pairing::bls12_381::{
+    Bls12, G1Affine, G1Compressed, G1Uncompressed, G2Affine, G2Compressed,
+    G2Uncompressed,
+};
+use pairing::Engine;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+/// One of the vectors of a [`USRS`].
+///
+/// [`USRS`]: ../usrs/struct.USRS.html
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SRSVector {
+    /// `g_x`
+    GX,
+    /// `g_ax`
+    GAX,
+    /// `h_x`
+    HX,
+    /// `h_ax`
+    HAX,
+}
+
+/// An element of a [`USRS`], identified by its vector and power of `x`.
+///
+/// [`USRS`]: ../usrs/struct.USRS.html
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SRSElement {
+    /// The vector containing the element.
+    pub vector: SRSVector,
+    /// The power of `x`, from `-d` to `d`.
+    pub power: isize,
+}
+
+impl fmt::Display for SRSElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.vector {
+            SRSVector::GX => write!(f, "g^{{x^{}}}", self.power),
+            SRSVector::GAX => write!(f, "g^{{alpha x^{}}}", self.power),
+            SRSVector::HX => write!(f, "h^{{x^{}}}", self.power),
+            SRSVector::HAX => write!(f, "h^{{alpha x^{}}}", self.power),
+        }
+    }
+}
+
+/// An error converting between a ceremony accumulator and a [`USRS`].
+///
+/// [`USRS`]: ../usrs/struct.USRS.html
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConversionError {
+    /// Elements of the SRS are unknown.
+    Missing {
+        /// The first missing element, in the order `g_x`, `g_ax`, `h_x`,
+        /// `h_ax`.
+        first: SRSElement,
+        /// The total number of missing elements.
+        count: usize,
+    },
+    /// A known element differs from the SRS being checked.
+    Mismatch(SRSElement),
+    /// The SRS being checked has a different dimension.
+    Dimension {
+        /// The dimension of the partial SRS.
+        expected: usize,
+        /// The dimension of the SRS being checked.
+        found: usize,
+    },
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConversionError::Missing { first, count } => write!(
+                f,
+                "{} SRS elements are missing, starting with {}",
+                count, first
+            ),
+            ConversionError::Mismatch(el) => {
+                write!(f, "SRS element {} does not match", el)
+            }
+            ConversionError::Dimension { expected, found } => write!(
+                f,
+                "SRS dimension {} does not match expected {}",
+                found, expected
+            ),
+        }
+    }
+}
+
+impl Error for ConversionError {}
+
+/// A [`USRS`] of which only some elements are known.
+///
+/// [`USRS`]: ../usrs/struct.USRS.html
+#[derive(Clone)]
+pub struct PartialUSRS<E: Engine> {
+    /// The dimension of the USRS.
+    pub d: usize,
+    /// g^{x^i} for i \in -d to d, where known
+    pub g_x: Vec<Option<E::G1Affine>>,
+    /// h^{x^i} for i \in -d to d, where known
+    pub h_x: Vec<Option<E::G2Affine>>,
+    /// g^{x^i \alpha} for i \in -d to d, where known; At i=0, the value is
+    /// ignored.
+    pub g_ax: Vec<Option<E::G1Affine>>,
+    /// h^{x^i \alpha} for i \in -d to d, where known
+    pub h_ax: Vec<Option<E::G2Affine>>,
+}
+
+impl<E: Engine> From<&USRS<E>> for PartialUSRS<E> {
+    fn from(srs: &USRS<E>) -> Self {
+        let mut g_ax = srs.g_ax.iter().cloned().map(Some).collect::<Vec<_>>();
+        g_ax[srs.d] = None;
+        PartialUSRS {
+            d: srs.d,
+            g_x: srs.g_x.iter().cloned().map(Some).collect(),
+            h_x: srs.h_x.iter().cloned().map(Some).collect(),
+            g_ax,
+            h_ax: srs.h_ax.iter().cloned().map(Some).collect(),
+        }
+    }
+}
+
+impl<E: Engine> PartialUSRS<E> {
+    /// Iterates over all elements, in the order `g_x`, `g_ax`, `h_x`, `h_ax`,
+    /// as their position, whether they are known, and whether they equal the
+    /// corresponding element of `srs`.
+    fn elements<'a>(
+        &'a self,
+        srs: Option<&'a USRS<E>>,
+    ) -> impl Iterator<Item = (SRSElement, bool, bool)> + 'a {
+        fn vector<'a, T: PartialEq>(
+            d: usize,
+            vector: SRSVector,
+            part: &'a [Option<T>],
+            full: Option<&'a [T]>,
+        ) -> impl Iterator<Item = (SRSElement, bool, bool)> + 'a {
+            part.iter().enumerate().map(move |(i, p)| {
+                let power = i as isize - d as isize;
+                let el = SRSElement { vector, power };
+                // g^\alpha is undefined, and always treated as known and
+                // matching.
+                if vector == SRSVector::GAX && power == 0 {
+                    return (el, true, true);
+                }
+                let eq = match (p, full) {
+                    (Some(p), Some(full)) => full.get(i) == Some(p),
+                    _ => true,
+                };
+                (el, p.is_some(), eq)
+            })
+        }
+        let d = self.d;
+        vector(d, SRSVector::GX, &self.g_x, srs.map(|s| &s.g_x[..]))
+            .chain(vector(
+                d,
+                SRSVector::GAX,
+                &self.g_ax,
+                srs.map(|s| &s.g_ax[..]),
+            ))
+            .chain(vector(d, SRSVector::HX, &self.h_x, srs.map(|s| &s.h_x[..])))
+            .chain(vector(
+                d,
+                SRSVector::HAX,
+                &self.h_ax,
+                srs.map(|s| &s.h_ax[..]),
+            ))
+    }
+
+    /// Reports the missing elements, if any.
+    pub fn missing(&self) -> Option<ConversionError> {
+        let mut missing = self.elements(None).filter(|(_, known, _)| !known);
+        missing
+            .next()
+            .map(|(first, _, _)| ConversionError::Missing {
+                first,
+                count: missing.count() + 1,
+            })
+    }
+
+    /// Converts into a full SRS, if all elements are known.
+    ///
+    /// The resulting SRS is not verified; see [`USRS::verify_structure`].
+    ///
+    /// [`USRS::verify_structure`]: ../usrs/struct.USRS.html#method.verify_structure
+    pub fn complete(self) -> Result<USRS<E>, ConversionError> {
+        if let Some(err) = self.missing() {
+            return Err(err);
+        }
+        let d = self.d;
+        let mut g_ax = self.g_ax;
+        g_ax[d] = Some(E::G1Affine::one());
+        Ok(USRS {
+            d,
+            g_x: self.g_x.into_iter().flatten().collect(),
+            h_x: self.h_x.into_iter().flatten().collect(),
+            g_ax: g_ax.into_iter().flatten().collect(),
+            h_ax: self.h_ax.into_iter().flatten().collect(),
+        })
+    }
+
+    /// Checks that every known element agrees with `srs`.
+    pub fn check(&self, srs: &USRS<E>) -> Result<(), ConversionError> {
+        if srs.d != self.d {
+            return Err(ConversionError::Dimension {
+                expected: self.d,
+                found: srs.d,
+            });
+        }
+        match self
+            .elements(Some(srs))
+            .find(|(_, _, eq)| !eq)
+            .map(|(el, _, _)| el)
+        {
+            Some(el) => Err(ConversionError::Mismatch(el)),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A BLS12-381 powers of tau accumulator, in the layout of Zcash's ceremony.
+///
+/// Files consist of the 64 byte hash of the previous response, followed by
+/// `tau_powers_g1`, `tau_powers_g2`, `alpha_tau_powers_g1`,
+/// `beta_tau_powers_g1` and `beta_g2`. Challenge files use uncompressed points,
+/// and response files compressed ones. Response files end with the
+/// contributor's public key, which is neither read nor written here.
+#[derive(Clone)]
+pub struct Accumulator {
+    /// The number of powers `n` in `tau_powers_g2`; the ceremony's
+    /// `TAU_POWERS_LENGTH`.
+    pub n: usize,
+    /// The BLAKE2b hash of the previous response file.
+    pub hash: [u8; 64],
+    /// g^{\tau^i} for i \in 0 to 2n - 2
+    pub tau_powers_g1: Vec<G1Affine>,
+    /// h^{\tau^i} for i \in 0 to n - 1
+    pub tau_powers_g2: Vec<G2Affine>,
+    /// g^{\alpha \tau^i} for i \in 0 to n - 1
+    pub alpha_tau_powers_g1: Vec<G1Affine>,
+    /// g^{\beta \tau^i} for i \in 0 to n - 1
+    pub beta_tau_powers_g1: Vec<G1Affine>,
+    /// h^\beta
+    pub beta_g2: G2Affine,
+}
+
+/// Maps the point at infinity, which marks missing elements, to `None`.
+fn known<G: CurveAffine>(g: &G) -> Option<G> {
+    if g.is_zero() {
+        None
+    } else {
+        Some(*g)
+    }
+}
+
+impl Accumulator {
+    /// Reads an accumulator with `n` powers of tau in G2.
+    ///
+    /// All points are checked to lie in the prime order subgroup, but the
+    /// accumulator's structure is not verified.
+    pub fn read<R: Read>(
+        mut inp: R,
+        n: usize,
+        compressed: bool,
+    ) -> Result<Self, ImportError> {
+        if n == 0 || n > (usize::MAX - 1) / 2 {
+            return Err(ImportError::InvalidLength(n as u64));
+        }
+        let mut hash = [0u8; 64];
+        inp.read_exact(&mut hash)?;
+        if compressed {
+            Self::read_points::<G1Compressed, G2Compressed, _>(inp, n, hash)
+        } else {
+            Self::read_points::<G1Uncompressed, G2Uncompressed, _>(inp, n, hash)
+        }
+    }
+
+    fn read_points<
+        P1: EncodedPoint<Affine = G1Affine>,
+        P2: EncodedPoint<Affine = G2Affine>,
+        R: Read,
+    >(
+        mut inp: R,
+        n: usize,
+        hash: [u8; 64],
+    ) -> Result<Self, ImportError> {
+        Ok(Accumulator {
+            n,
+            hash,
+            tau_powers_g1: read_points::<P1, _>(&mut inp, 2 * n - 1)?,
+            tau_powers_g2: read_points::<P2, _>(&mut inp, n)?,
+            alpha_tau_powers_g1: read_points::<P1, _>(&mut inp, n)?,
+            beta_tau_powers_g1: read_points::<P1, _>(&mut inp, n)?,
+            beta_g2: read_points::<P2, _>(&mut inp, 1)?[0],
+        })
+    }
+
+    /// Writes the accumulator.
+    pub fn write<W: Write>(&self, out: W, compressed: bool) -> io::Result<()> {
+        if compressed {
+            self.write_points::<G1Compressed, G2Compressed, _>(out)
+        } else {
+            self.write_points::<G1Uncompressed, G2Uncompressed, _>(out)
+        }
+    }
+
+    fn write_points<
+        P1: EncodedPoint<Affine = G1Affine>,
+        P2: EncodedPoint<Affine = G2Affine>,
+        W: Write,
+    >(
+        &self,
+        mut out: W,
+    ) -> io::Result<()> {
+        out.write_all(&self.hash)?;
+        for g in self.tau_powers_g1.iter() {
+            out.write_all(P1::from_affine(*g).as_ref())?;
+        }
+        for h in self.tau_powers_g2.iter() {
+            out.write_all(P2::from_affine(*h).as_ref())?;
+        }
+        for v in [&self.alpha_tau_powers_g1, &self.beta_tau_powers_g1].iter() {
+            for g in v.iter() {
+                out.write_all(P1::from_affine(*g).as_ref())?;
+            }
+        }
+        out.write_all(P2::from_affine(self.beta_g2).as_ref())
+    }
+
+    /// Maps an SRS to an accumulator with `n` powers of tau in G2, and the
+    /// given previous response hash.
+    ///
+    /// Elements the SRS does not determine, namely `g^\alpha`, the `\beta`
+    /// elements, and powers beyond its dimension, are set to the point at
+    /// infinity.
+    pub fn from_usrs(srs: &USRS<Bls12>, n: usize, hash: [u8; 64]) -> Self {
+        assert!(n >= 1);
+        let d = srs.d;
+        let g_at = |v: &[G1Affine], i: usize| {
+            if i <= d {
+                v[d + i]
+            } else {
+                G1Affine::zero()
+            }
+        };
+        let mut alpha_tau_powers_g1 =
+            (0..n).map(|i| g_at(&srs.g_ax, i)).collect::<Vec<_>>();
+        alpha_tau_powers_g1[0] = G1Affine::zero();
+        Accumulator {
+            n,
+            hash,
+            tau_powers_g1: (0..2 * n - 1).map(|i| g_at(&srs.g_x, i)).collect(),
+            tau_powers_g2: (0..n)
+                .map(|i| {
+                    if i <= d {
+                        srs.h_x[d + i]
+                    } else {
+                        G2Affine::zero()
+                    }
+                })
+                .collect(),
+            alpha_tau_powers_g1,
+            beta_tau_powers_g1: vec![G1Affine::zero(); n],
+            beta_g2: G2Affine::zero(),
+        }
+    }
+
+    /// Maps the accumulator to the elements of an SRS of dimension `d` that it
+    /// determines.
+    ///
+    /// Elements set to the point at infinity are treated as missing.
+    pub fn to_usrs(&self, d: usize) -> PartialUSRS<Bls12> {
+        fn positive<G: CurveAffine>(d: usize, powers: &[G]) -> Vec<Option<G>> {
+            let mut v = vec![None; 2 * d + 1];
+            for (i, g) in powers.iter().take(d + 1).enumerate() {
+                v[d + i] = known(g);
+            }
+            v
+        }
+        PartialUSRS {
+            d,
+            g_x: positive(d, &self.tau_powers_g1),
+            h_x: positive(d, &self.tau_powers_g2),
+            g_ax: positive(d, &self.alpha_tau_powers_g1),
+            h_ax: vec![None; 2 * d + 1],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ro::RO;
+    use crate::usrs::Trapdoor;
+    use ff::Field;
+    use group::CurveProjective;
+    use pairing::bls12_381::Fr;
+    use rand::Rng;
+    use sha3::Sha3_256;
+
+    /// `g^{c \tau^i}` for `i` from 0 to `n - 1`.
+    fn powers<G: CurveAffine<Scalar = Fr>>(c: Fr, tau: Fr, n: usize) -> Vec<G> {
+        let mut s = c;
+        (0..n)
+            .map(|_| {
+                let g = G::one().mul(s).into_affine();
+                s.mul_assign(&tau);
+                g
+            })
+            .collect()
+    }
+
+    /// A freshly generated accumulator with `n` powers, and its trapdoor.
+    fn accumulator(n: usize) -> (Accumulator, Trapdoor<Bls12>) {
+        let mut rng = Sha3_256::query(b"zcash test").into_rng();
+        let trapdoor: Trapdoor<Bls12> = rng.gen();
+        let (tau, alpha) = (trapdoor.x, trapdoor.alpha);
+        let beta = Fr::random(&mut rng);
+        let acc = Accumulator {
+            n,
+            hash: [7u8; 64],
+            tau_powers_g1: powers(Fr::one(), tau, 2 * n - 1),
+            tau_powers_g2: powers(Fr::one(), tau, n),
+            alpha_tau_powers_g1: powers(alpha, tau, n),
+            beta_tau_powers_g1: powers(beta, tau, n),
+            beta_g2: G2Affine::one().mul(beta).into_affine(),
+        };
+        (acc, trapdoor)
+    }
+
+    fn encode(acc: &Accumulator, compressed: bool) -> Vec<u8> {
+        let mut buf = Vec::new();
+        acc.write(&mut buf, compressed).unwrap();
+        buf
+    }
+
+    #[test]
+    fn accumulator_round_trip() {
+        let (acc, _) = accumulator(3);
+        for compressed in [true, false].iter() {
+            let buf = encode(&acc, *compressed);
+            let read = Accumulator::read(&buf[..], 3, *compressed).unwrap();
+            assert_eq!(encode(&read, *compressed), buf);
+            assert!(matches!(
+                Accumulator::read(&buf[..buf.len() - 1], 3, *compressed),
+                Err(ImportError::Truncated)
+            ));
+        }
+        assert!(matches!(
+            Accumulator::read(&[][..], 0, true),
+            Err(ImportError::InvalidLength(0))
+        ));
+    }
+
+    #[test]
+    fn accumulator_matches_srs() {
+        let (acc, trapdoor) = accumulator(3);
+        let srs = USRS::<Bls12>::new(2).permute(&trapdoor);
+        let partial = acc.to_usrs(2);
+        assert_eq!(partial.check(&srs), Ok(()));
+        // The accumulator has no negative powers, nor h_ax.
+        assert_eq!(
+            partial.missing(),
+            Some(ConversionError::Missing {
+                first: SRSElement {
+                    vector: SRSVector::GX,
+                    power: -2,
+                },
+                count: 2 + 2 + 2 + 5,
+            })
+        );
+        assert_eq!(partial.clone().complete().err(), partial.missing());
+        // The positive powers survive a round trip through an accumulator.
+        let exported = Accumulator::from_usrs(&srs, 3, [0u8; 64]);
+        assert_eq!(exported.tau_powers_g1[..3], acc.tau_powers_g1[..3]);
+        assert!(exported.tau_powers_g1[3..].iter().all(|g| g.is_zero()));
+        assert_eq!(exported.to_usrs(2).check(&srs), Ok(()));
+    }
+
+    #[test]
+    fn check_reports_mismatch_and_dimension() {
+        let (acc, _) = accumulator(3);
+        let mut rng = Sha3_256::query(b"zcash mismatch").into_rng();
+        let other = USRS::<Bls12>::new(2).permute(&rng.gen());
+        // g^{x^0} is the generator in both, so x^1 is the first to differ.
+        assert_eq!(
+            acc.to_usrs(2).check(&other),
+            Err(ConversionError::Mismatch(SRSElement {
+                vector: SRSVector::GX,
+                power: 1,
+            }))
+        );
+        let larger = USRS::<Bls12>::new(3).permute(&rng.gen());
+        assert_eq!(
+            acc.to_usrs(2).check(&larger),
+            Err(ConversionError::Dimension {
+                expected: 2,
+                found: 3,
+            })
+        );
+    }
+
+    #[test]
+    fn partial_completes_when_all_known() {
+        let mut rng = Sha3_256::query(b"zcash complete").into_rng();
+        let srs = USRS::<Bls12>::new(2).permute(&rng.gen());
+        let partial = PartialUSRS::from(&srs);
+        assert_eq!(partial.missing(), None);
+        assert_eq!(partial.check(&srs), Ok(()));
+        assert_eq!(partial.complete().unwrap().digest(), srs.digest());
+    }
+}